@@ -0,0 +1,313 @@
+use std::{error::Error, path::Path, sync::Arc, time::Duration};
+
+use sysinfo::System;
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+
+use super::types::{
+    Cli, ClientArgs, Command, ConfigFile, LogsArgs, ManagerArgs, RunArgs, WorkerArgs,
+};
+use crate::lib::{
+    client::types::Client,
+    manager::types::{Manager, ManagerServer, ManagerStores},
+    scheduler::types::SchedulerType,
+    store::types::FileStore,
+    tasks::{
+        manifest::load_all,
+        runtime::Runtime,
+        types::{DockerClient, LogOptions, MemoryRuntime, ProcessRuntime, Task},
+    },
+    worker::{
+        health::check_health,
+        registration::register_with_manager,
+        types::{TaskServer, Worker},
+        worker::{collect_garbage, collect_stats, monitor_tasks, run_tasks},
+    },
+};
+
+// * Runs the subcommand with its options completed from the config file
+pub async fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
+    let config = ConfigFile::load(cli.config.as_deref())?;
+
+    match cli.command {
+        Command::Manager(args) => run_manager(args.merge(config.manager)).await,
+        Command::Worker(args) => run_worker(args.merge(config.worker)).await,
+        Command::Run(args) => {
+            let client = client(&args.client, config.client);
+            submit(&client, args).await
+        }
+        Command::Apply { path, client: args } => apply(&client(&args, config.client), &path).await,
+        Command::Scale {
+            service,
+            replicas,
+            client: args,
+        } => {
+            let status = client(&args, config.client)
+                .scale(&service, replicas)
+                .await?;
+            println!(
+                "Service {} scaled to {} replicas ({} running)",
+                status.service.name, status.service.replicas, status.running
+            );
+            Ok(())
+        }
+        Command::Rollback {
+            service,
+            client: args,
+        } => {
+            let status = client(&args, config.client).rollback(&service).await?;
+            println!(
+                "Service {} rolling out revision {}",
+                status.service.name, status.service.revision
+            );
+            Ok(())
+        }
+        Command::Stop { id, client: args } => {
+            let message = client(&args, config.client).stop(&id).await?;
+            println!("{}", message);
+            Ok(())
+        }
+        Command::Status { id, client: args } => status(&client(&args, config.client), id).await,
+        Command::Logs(args) => {
+            let client = client(&args.client, config.client);
+            logs(&client, args).await
+        }
+    }
+}
+
+fn client(args: &ClientArgs, file: ClientArgs) -> Client {
+    Client::new(args.clone().merge(file).manager())
+}
+
+pub async fn run_manager(args: ManagerArgs) -> Result<(), Box<dyn Error>> {
+    let scheduler = args
+        .scheduler
+        .as_deref()
+        .map(str::parse::<SchedulerType>)
+        .transpose()?
+        .unwrap_or_default();
+    let workers = args.workers.clone().unwrap_or_default();
+
+    let manager = match &args.data_dir {
+        Some(dir) => {
+            let stores = ManagerStores::open(&dir.join("manager"))?;
+            Manager::with_stores(workers, scheduler, stores)
+        }
+        None => Manager::new(workers, scheduler),
+    };
+
+    let manager = Arc::new(Mutex::new(manager));
+    let server = ManagerServer::new(
+        manager.clone(),
+        args.address.as_deref().unwrap_or("localhost"),
+        &args.port.unwrap_or(8081).to_string(),
+    );
+
+    let cancel = CancellationToken::new();
+    let reconciler = tokio::spawn(Manager::run(
+        manager,
+        args.intervals.resolve(),
+        cancel.clone(),
+    ));
+
+    tokio::select! {
+        _ = server.start_server() => {}
+        _ = tokio::signal::ctrl_c() => {
+            println!("Shutting down");
+        }
+    }
+
+    cancel.cancel();
+    reconciler.await?;
+    Ok(())
+}
+
+pub async fn run_worker(args: WorkerArgs) -> Result<(), Box<dyn Error>> {
+    match args.runtime.as_deref().unwrap_or("docker") {
+        "docker" => {
+            let mut docker = DockerClient::new().ok_or("Failed to create Docker client")?;
+            if let Some(path) = &args.registry_auth {
+                docker = docker
+                    .with_credentials_file(path)
+                    .map_err(|e| e.to_string())?;
+            }
+            serve_worker(docker, args).await
+        }
+        "process" => serve_worker(ProcessRuntime::new(), args).await,
+        "memory" => serve_worker(MemoryRuntime::new(), args).await,
+        other => Err(format!("Unknown runtime: {}", other).into()),
+    }
+}
+
+async fn serve_worker<R: Runtime>(runtime: R, args: WorkerArgs) -> Result<(), Box<dyn Error>> {
+    let name = args
+        .name
+        .clone()
+        .or_else(System::host_name)
+        .unwrap_or_else(|| "worker".to_string());
+    let address = args.address.as_deref().unwrap_or("localhost");
+    let port = args.port.unwrap_or(8080);
+    let seconds = |value: Option<u64>, default: u64| Duration::from_secs(value.unwrap_or(default));
+
+    let mut worker = match &args.data_dir {
+        Some(dir) => {
+            let tasks = FileStore::open(dir.join("worker").join("tasks.log"))?;
+            Worker::with_store(&name, runtime, Arc::new(tasks))
+        }
+        None => Worker::new(&name, runtime),
+    };
+    if let Some(max) = args.max_tasks {
+        worker.max_concurrent_tasks = max;
+    }
+    if let Some(allow) = args.allow_exec {
+        worker.allow_exec = allow;
+    }
+    if let Some(remove) = args.remove_on_stop {
+        worker.remove_on_stop = remove;
+    }
+    if let Some(retention) = args.container_retention {
+        worker.container_retention = Duration::from_secs(retention);
+    }
+    for label in args.labels.iter().flatten() {
+        let (key, value) = label
+            .split_once('=')
+            .ok_or_else(|| format!("Label {:?} is not KEY=VALUE", label))?;
+        worker.labels.insert(key.to_string(), value.to_string());
+    }
+
+    let worker = Arc::new(Mutex::new(worker));
+    worker.lock().await.recover().await;
+    let server = TaskServer::new(worker.clone(), address, &port.to_string());
+
+    tokio::spawn(register_with_manager(
+        worker.clone(),
+        args.manager
+            .clone()
+            .unwrap_or_else(|| "localhost:8081".to_string()),
+        args.advertise
+            .clone()
+            .unwrap_or_else(|| format!("{}:{}", address, port)),
+        seconds(args.heartbeat_interval, 10),
+    ));
+
+    {
+        let worker = worker.clone();
+        let sysinfo_worker = worker.clone();
+        let monitor_worker = worker.clone();
+        let health_worker = worker.clone();
+        let garbage_worker = worker.clone();
+        let monitor_interval = seconds(args.monitor_interval, 5);
+        let gc_interval = seconds(args.gc_interval, 30);
+        tokio::spawn(async move {
+            let stats_task = collect_stats(worker);
+            let tasks_task = run_tasks(sysinfo_worker);
+            let monitor_task = monitor_tasks(monitor_worker, monitor_interval);
+            let health_task = check_health(health_worker);
+            let garbage_task = collect_garbage(garbage_worker, gc_interval);
+            tokio::join!(
+                stats_task,
+                tasks_task,
+                monitor_task,
+                health_task,
+                garbage_task
+            );
+        });
+    }
+
+    tokio::select! {
+        _ = server.start_server() => {}
+        _ = tokio::signal::ctrl_c() => {
+            println!("Shutting down");
+        }
+    }
+    Ok(())
+}
+
+async fn submit(client: &Client, args: RunArgs) -> Result<(), Box<dyn Error>> {
+    let mut task = match &args.file {
+        Some(path) => {
+            let contents = std::fs::read_to_string(path)
+                .map_err(|e| format!("Unable to read {}: {}", path.display(), e))?;
+            serde_json::from_str(&contents)
+                .map_err(|e| format!("Invalid task in {}: {}", path.display(), e))?
+        }
+        None => Task::default(),
+    };
+
+    if let Some(name) = args.name {
+        task.name = name;
+    }
+    if let Some(image) = args.image {
+        task.image = image;
+    }
+    task.env.extend(args.env);
+    if let Some(cpu) = args.cpu {
+        task.cpu = cpu;
+    }
+    if let Some(memory) = args.memory {
+        task.memory = memory;
+    }
+    if !args.command.is_empty() {
+        task.command = args.command;
+    }
+    if task.name.is_empty() {
+        task.name = task.image.clone();
+    }
+
+    let task = client.run(task).await?;
+    println!("Task {} queued", task.id);
+    Ok(())
+}
+
+// * Validates the manifests locally so every error shows up at once, then
+// * applies them
+async fn apply(client: &Client, path: &Path) -> Result<(), Box<dyn Error>> {
+    let manifests = load_all(path).map_err(|errors| {
+        let errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+        format!("Invalid manifests:\n{}", errors.join("\n"))
+    })?;
+
+    for result in client.apply(&manifests).await? {
+        println!("{} {} ({})", result.name, result.action, result.task_id);
+    }
+    Ok(())
+}
+
+async fn status(client: &Client, id: Option<String>) -> Result<(), Box<dyn Error>> {
+    if let Some(id) = id {
+        let task = client.task(&id).await?;
+        println!("{}", serde_json::to_string_pretty(&task)?);
+        return Ok(());
+    }
+
+    let mut tasks = client.tasks().await?;
+    tasks.sort_by(|a, b| a.name.cmp(&b.name).then(a.id.cmp(&b.id)));
+    println!(
+        "{:<36}  {:<20}  {:<10}  {:<8}  IMAGE",
+        "ID", "NAME", "STATE", "HEALTH"
+    );
+    for task in tasks {
+        println!(
+            "{:<36}  {:<20}  {:<10}  {:<8}  {}",
+            task.id,
+            task.name,
+            format!("{:?}", task.state),
+            format!("{:?}", task.health),
+            task.image
+        );
+    }
+    Ok(())
+}
+
+async fn logs(client: &Client, args: LogsArgs) -> Result<(), Box<dyn Error>> {
+    let options = LogOptions {
+        follow: args.follow,
+        tail: args.tail,
+        since: args.since,
+        ..Default::default()
+    };
+    client
+        .logs(&args.id, &options, &mut std::io::stdout())
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,124 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use super::types::{ClientArgs, ConfigFile, IntervalArgs, ManagerArgs, WorkerArgs};
+use crate::lib::manager::types::ManagerIntervals;
+
+pub const DEFAULT_CONFIG_FILE: &str = "r_cube.toml";
+pub const DEFAULT_MANAGER: &str = "localhost:8081";
+
+impl ConfigFile {
+    // * Reads the config file at `path`, or ./r_cube.toml when no path was
+    // * given and it exists. No file at all is an empty config.
+    pub fn load(path: Option<&Path>) -> Result<Self, String> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => {
+                let default = PathBuf::from(DEFAULT_CONFIG_FILE);
+                if !default.exists() {
+                    return Ok(ConfigFile::default());
+                }
+                default
+            }
+        };
+
+        let contents = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Unable to read config file {}: {}", path.display(), e))?;
+        toml::from_str(&contents)
+            .map_err(|e| format!("Invalid config file {}: {}", path.display(), e))
+    }
+}
+
+impl ManagerArgs {
+    // * Fills the options left out on the command line from the config file
+    pub fn merge(self, file: ManagerArgs) -> ManagerArgs {
+        ManagerArgs {
+            address: self.address.or(file.address),
+            port: self.port.or(file.port),
+            workers: self.workers.or(file.workers),
+            scheduler: self.scheduler.or(file.scheduler),
+            data_dir: self.data_dir.or(file.data_dir),
+            intervals: self.intervals.merge(file.intervals),
+        }
+    }
+}
+
+impl IntervalArgs {
+    pub fn merge(self, file: IntervalArgs) -> IntervalArgs {
+        IntervalArgs {
+            send_work: self.send_work.or(file.send_work),
+            update_tasks: self.update_tasks.or(file.update_tasks),
+            check_workers: self.check_workers.or(file.check_workers),
+            update_nodes: self.update_nodes.or(file.update_nodes),
+            restart_tasks: self.restart_tasks.or(file.restart_tasks),
+            reconcile_services: self.reconcile_services.or(file.reconcile_services),
+            restart_backoff: self.restart_backoff.or(file.restart_backoff),
+            max_restart_backoff: self.max_restart_backoff.or(file.max_restart_backoff),
+            heartbeat_timeout: self.heartbeat_timeout.or(file.heartbeat_timeout),
+            worker_expiry: self.worker_expiry.or(file.worker_expiry),
+            worker_failure_threshold: self
+                .worker_failure_threshold
+                .or(file.worker_failure_threshold),
+        }
+    }
+
+    pub fn resolve(&self) -> ManagerIntervals {
+        let defaults = ManagerIntervals::default();
+        let seconds = |value: Option<u64>, default: Duration| {
+            value.map(Duration::from_secs).unwrap_or(default)
+        };
+
+        ManagerIntervals {
+            send_work: seconds(self.send_work, defaults.send_work),
+            update_tasks: seconds(self.update_tasks, defaults.update_tasks),
+            check_workers: seconds(self.check_workers, defaults.check_workers),
+            update_nodes: seconds(self.update_nodes, defaults.update_nodes),
+            restart_tasks: seconds(self.restart_tasks, defaults.restart_tasks),
+            reconcile_services: seconds(self.reconcile_services, defaults.reconcile_services),
+            restart_backoff: seconds(self.restart_backoff, defaults.restart_backoff),
+            max_restart_backoff: seconds(self.max_restart_backoff, defaults.max_restart_backoff),
+            heartbeat_timeout: seconds(self.heartbeat_timeout, defaults.heartbeat_timeout),
+            worker_expiry: seconds(self.worker_expiry, defaults.worker_expiry),
+            worker_failure_threshold: self
+                .worker_failure_threshold
+                .unwrap_or(defaults.worker_failure_threshold),
+        }
+    }
+}
+
+impl WorkerArgs {
+    pub fn merge(self, file: WorkerArgs) -> WorkerArgs {
+        WorkerArgs {
+            name: self.name.or(file.name),
+            address: self.address.or(file.address),
+            port: self.port.or(file.port),
+            advertise: self.advertise.or(file.advertise),
+            manager: self.manager.or(file.manager),
+            runtime: self.runtime.or(file.runtime),
+            data_dir: self.data_dir.or(file.data_dir),
+            max_tasks: self.max_tasks.or(file.max_tasks),
+            allow_exec: self.allow_exec.or(file.allow_exec),
+            remove_on_stop: self.remove_on_stop.or(file.remove_on_stop),
+            container_retention: self.container_retention.or(file.container_retention),
+            registry_auth: self.registry_auth.or(file.registry_auth),
+            heartbeat_interval: self.heartbeat_interval.or(file.heartbeat_interval),
+            monitor_interval: self.monitor_interval.or(file.monitor_interval),
+            gc_interval: self.gc_interval.or(file.gc_interval),
+            labels: self.labels.or(file.labels),
+        }
+    }
+}
+
+impl ClientArgs {
+    pub fn merge(self, file: ClientArgs) -> ClientArgs {
+        ClientArgs {
+            manager: self.manager.or(file.manager),
+        }
+    }
+
+    pub fn manager(&self) -> &str {
+        self.manager.as_deref().unwrap_or(DEFAULT_MANAGER)
+    }
+}
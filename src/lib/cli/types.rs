@@ -0,0 +1,241 @@
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand};
+use serde::Deserialize;
+
+// * Cli is the command line of the r_cube binary. Options missing from the
+// * command line are read from their environment variable, then from the
+// * config file, and fall back to a default last.
+#[derive(Parser, Debug)]
+#[command(name = "r_cube", version, about = "A small container orchestrator")]
+pub struct Cli {
+    /// TOML file with [manager], [worker] and [client] tables [default: ./r_cube.toml if present]
+    #[arg(long, short, global = true, env = "R_CUBE_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Runs a manager, which places tasks on its workers
+    Manager(ManagerArgs),
+    /// Runs a worker, which starts the tasks its manager sends
+    Worker(WorkerArgs),
+    /// Submits a task to the manager
+    Run(RunArgs),
+    /// Creates or updates tasks from a manifest file or a directory of them
+    Apply {
+        /// .yaml, .yml or .toml manifest, or a directory holding them
+        path: PathBuf,
+        #[command(flatten)]
+        client: ClientArgs,
+    },
+    /// Sets how many tasks of a service run
+    Scale {
+        /// Id or name of the service
+        service: String,
+        replicas: u32,
+        #[command(flatten)]
+        client: ClientArgs,
+    },
+    /// Rolls a service back to its previous revision
+    Rollback {
+        /// Id or name of the service
+        service: String,
+        #[command(flatten)]
+        client: ClientArgs,
+    },
+    /// Stops a task
+    Stop {
+        id: String,
+        #[command(flatten)]
+        client: ClientArgs,
+    },
+    /// Shows every task, or one task in detail
+    Status {
+        id: Option<String>,
+        #[command(flatten)]
+        client: ClientArgs,
+    },
+    /// Prints the logs of a task
+    Logs(LogsArgs),
+}
+
+// * ConfigFile is the TOML config file. Each table takes the same options as
+// * the matching subcommand, named like its flags with underscores.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigFile {
+    pub manager: ManagerArgs,
+    pub worker: WorkerArgs,
+    pub client: ClientArgs,
+}
+
+#[derive(Args, Deserialize, Debug, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct ManagerArgs {
+    /// Address the manager API listens on [default: localhost]
+    #[arg(long, env = "R_CUBE_MANAGER_ADDRESS")]
+    pub address: Option<String>,
+    /// Port the manager API listens on [default: 8081]
+    #[arg(long, env = "R_CUBE_MANAGER_PORT")]
+    pub port: Option<u16>,
+    /// Workers to use without them registering, as host:port
+    #[arg(long, env = "R_CUBE_WORKERS", value_delimiter = ',')]
+    pub workers: Option<Vec<String>>,
+    /// roundrobin, greedy or epvm [default: roundrobin]
+    #[arg(long, env = "R_CUBE_SCHEDULER")]
+    pub scheduler: Option<String>,
+    /// Keeps the manager's state in this directory across restarts
+    #[arg(long, env = "R_CUBE_DATA_DIR")]
+    pub data_dir: Option<PathBuf>,
+    #[command(flatten)]
+    pub intervals: IntervalArgs,
+}
+
+// * IntervalArgs are the timings of the manager's reconciliation loop, in
+// * seconds. They default to `ManagerIntervals::default()`.
+#[derive(Args, Deserialize, Debug, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct IntervalArgs {
+    /// Seconds between two rounds of sending queued events to workers
+    #[arg(long = "send-work-interval", env = "R_CUBE_SEND_WORK_INTERVAL")]
+    pub send_work: Option<u64>,
+    /// Seconds between two polls of the workers' tasks
+    #[arg(long = "update-tasks-interval", env = "R_CUBE_UPDATE_TASKS_INTERVAL")]
+    pub update_tasks: Option<u64>,
+    /// Seconds between two checks of the workers' heartbeats
+    #[arg(long = "check-workers-interval", env = "R_CUBE_CHECK_WORKERS_INTERVAL")]
+    pub check_workers: Option<u64>,
+    /// Seconds between two polls of the workers' stats
+    #[arg(long = "update-nodes-interval", env = "R_CUBE_UPDATE_NODES_INTERVAL")]
+    pub update_nodes: Option<u64>,
+    /// Seconds between two checks for tasks to restart
+    #[arg(long = "restart-tasks-interval", env = "R_CUBE_RESTART_TASKS_INTERVAL")]
+    pub restart_tasks: Option<u64>,
+    /// Seconds between two rounds of matching services to their replica count
+    #[arg(
+        long = "reconcile-services-interval",
+        env = "R_CUBE_RECONCILE_SERVICES_INTERVAL"
+    )]
+    pub reconcile_services: Option<u64>,
+    /// Seconds before the first restart of a task, doubled for each later one
+    #[arg(long, env = "R_CUBE_RESTART_BACKOFF")]
+    pub restart_backoff: Option<u64>,
+    /// Most seconds between two restarts of a task
+    #[arg(long, env = "R_CUBE_MAX_RESTART_BACKOFF")]
+    pub max_restart_backoff: Option<u64>,
+    /// Seconds without a heartbeat before a worker is unhealthy
+    #[arg(long, env = "R_CUBE_HEARTBEAT_TIMEOUT")]
+    pub heartbeat_timeout: Option<u64>,
+    /// Seconds without a heartbeat before a worker is removed
+    #[arg(long, env = "R_CUBE_WORKER_EXPIRY")]
+    pub worker_expiry: Option<u64>,
+    /// Failed polls in a row before a worker is down and its tasks move
+    #[arg(long, env = "R_CUBE_WORKER_FAILURE_THRESHOLD")]
+    pub worker_failure_threshold: Option<u32>,
+}
+
+#[derive(Args, Deserialize, Debug, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct WorkerArgs {
+    /// Name the worker registers under [default: the host name]
+    #[arg(long, env = "R_CUBE_WORKER_NAME")]
+    pub name: Option<String>,
+    /// Address the worker API listens on [default: localhost]
+    #[arg(long, env = "R_CUBE_WORKER_ADDRESS")]
+    pub address: Option<String>,
+    /// Port the worker API listens on [default: 8080]
+    #[arg(long, env = "R_CUBE_WORKER_PORT")]
+    pub port: Option<u16>,
+    /// host:port the manager reaches the worker at [default: address:port]
+    #[arg(long, env = "R_CUBE_WORKER_ADVERTISE")]
+    pub advertise: Option<String>,
+    /// host:port of the manager to register with [default: localhost:8081]
+    #[arg(long, env = "R_CUBE_MANAGER")]
+    pub manager: Option<String>,
+    /// docker, process or memory [default: docker]
+    #[arg(long, env = "R_CUBE_RUNTIME")]
+    pub runtime: Option<String>,
+    /// Keeps the worker's tasks in this directory across restarts
+    #[arg(long, env = "R_CUBE_DATA_DIR")]
+    pub data_dir: Option<PathBuf>,
+    /// Tasks started or stopped at the same time [default: 4]
+    #[arg(long, env = "R_CUBE_MAX_TASKS")]
+    pub max_tasks: Option<usize>,
+    /// Lets clients run commands inside task containers [default: false]
+    #[arg(long, env = "R_CUBE_ALLOW_EXEC", num_args = 0..=1, default_missing_value = "true")]
+    pub allow_exec: Option<bool>,
+    /// Removes the container of a task once it is stopped [default: true]
+    #[arg(long, env = "R_CUBE_REMOVE_ON_STOP", num_args = 0..=1, default_missing_value = "true")]
+    pub remove_on_stop: Option<bool>,
+    /// Seconds the containers of finished tasks are kept [default: 600]
+    #[arg(long, env = "R_CUBE_CONTAINER_RETENTION")]
+    pub container_retention: Option<u64>,
+    /// JSON file of registry credentials, keyed by registry host
+    #[arg(long, env = "R_CUBE_REGISTRY_AUTH")]
+    pub registry_auth: Option<PathBuf>,
+    /// Seconds between two heartbeats to the manager [default: 10]
+    #[arg(long, env = "R_CUBE_HEARTBEAT_INTERVAL")]
+    pub heartbeat_interval: Option<u64>,
+    /// Seconds between two checks for exited containers [default: 5]
+    #[arg(long, env = "R_CUBE_MONITOR_INTERVAL")]
+    pub monitor_interval: Option<u64>,
+    /// Seconds between two garbage collections of containers [default: 30]
+    #[arg(long, env = "R_CUBE_GC_INTERVAL")]
+    pub gc_interval: Option<u64>,
+    /// Label for task placement, as KEY=VALUE, on top of "arch" and "os"
+    #[arg(long = "label", env = "R_CUBE_WORKER_LABELS", value_delimiter = ',')]
+    pub labels: Option<Vec<String>>,
+}
+
+#[derive(Args, Deserialize, Debug, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClientArgs {
+    /// host:port of the manager [default: localhost:8081]
+    #[arg(long, env = "R_CUBE_MANAGER")]
+    pub manager: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct RunArgs {
+    /// JSON file with the task to run. Flags override its fields.
+    #[arg(long, short)]
+    pub file: Option<PathBuf>,
+    #[arg(long)]
+    pub name: Option<String>,
+    #[arg(long)]
+    pub image: Option<String>,
+    /// Environment variable for the task, as KEY=VALUE
+    #[arg(long, short)]
+    pub env: Vec<String>,
+    /// CPUs the task needs
+    #[arg(long)]
+    pub cpu: Option<f64>,
+    /// Bytes of memory the task needs
+    #[arg(long)]
+    pub memory: Option<u64>,
+    /// Command to run instead of the image's entrypoint, with its arguments
+    #[arg(last = true)]
+    pub command: Vec<String>,
+    #[command(flatten)]
+    pub client: ClientArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct LogsArgs {
+    pub id: String,
+    /// Keeps printing new lines until the task's container exits
+    #[arg(long, short)]
+    pub follow: bool,
+    /// Only the last lines of the logs
+    #[arg(long)]
+    pub tail: Option<usize>,
+    /// Only lines printed after this Unix timestamp, in seconds
+    #[arg(long)]
+    pub since: Option<u64>,
+    #[command(flatten)]
+    pub client: ClientArgs,
+}
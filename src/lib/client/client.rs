@@ -0,0 +1,118 @@
+use std::io::Write;
+
+use serde::de::DeserializeOwned;
+
+use super::types::{Client, ClientError, ClientResult};
+use crate::lib::{
+    manager::types::{ApplyResult, ScaleRequest, ServiceStatus},
+    tasks::types::{EventKind, LogOptions, Manifest, Task, TaskEvent},
+};
+
+impl Client {
+    pub fn new(manager: &str) -> Self {
+        Client {
+            http: reqwest::Client::new(),
+            manager: manager.to_string(),
+        }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.manager, path)
+    }
+
+    async fn send(&self, request: reqwest::RequestBuilder) -> ClientResult<reqwest::Response> {
+        let response = request
+            .send()
+            .await
+            .map_err(|e| ClientError::Connection(format!("{}: {}", self.manager, e)))?;
+
+        if response.status().is_success() {
+            return Ok(response);
+        }
+        let status = response.status().as_u16();
+        let message = response.text().await.unwrap_or_default();
+        Err(ClientError::Rejected { status, message })
+    }
+
+    async fn json<T: DeserializeOwned>(&self, request: reqwest::RequestBuilder) -> ClientResult<T> {
+        self.send(request)
+            .await?
+            .json()
+            .await
+            .map_err(|e| ClientError::InvalidResponse(e.to_string()))
+    }
+
+    // * Queues the task on the manager, which places it on a worker
+    pub async fn run(&self, task: Task) -> ClientResult<Task> {
+        let event = TaskEvent::new(EventKind::Start, task);
+        self.json(self.http.post(self.url("/tasks")).json(&event))
+            .await
+    }
+
+    // * Creates or updates the manifests' tasks on the manager
+    pub async fn apply(&self, manifests: &[Manifest]) -> ClientResult<Vec<ApplyResult>> {
+        self.json(self.http.post(self.url("/apply")).json(manifests))
+            .await
+    }
+
+    // * Sets the replica count of a service, by id or name
+    pub async fn scale(&self, service: &str, replicas: u32) -> ClientResult<ServiceStatus> {
+        let request = self
+            .http
+            .put(self.url(&format!("/services/{}/scale", service)))
+            .json(&ScaleRequest { replicas });
+        self.json(request).await
+    }
+
+    // * Rolls a service back to its previous revision, by id or name
+    pub async fn rollback(&self, service: &str) -> ClientResult<ServiceStatus> {
+        let request = self
+            .http
+            .post(self.url(&format!("/services/{}/rollback", service)));
+        self.json(request).await
+    }
+
+    pub async fn stop(&self, task_id: &str) -> ClientResult<String> {
+        let request = self.http.delete(self.url(&format!("/tasks/{}", task_id)));
+        self.send(request)
+            .await?
+            .text()
+            .await
+            .map_err(|e| ClientError::InvalidResponse(e.to_string()))
+    }
+
+    pub async fn tasks(&self) -> ClientResult<Vec<Task>> {
+        self.json(self.http.get(self.url("/tasks"))).await
+    }
+
+    pub async fn task(&self, task_id: &str) -> ClientResult<Task> {
+        self.json(self.http.get(self.url(&format!("/tasks/{}", task_id))))
+            .await
+    }
+
+    // * Copies the task's logs to `out` as they arrive, which with `follow`
+    // * lasts until the task's container exits
+    pub async fn logs(
+        &self,
+        task_id: &str,
+        options: &LogOptions,
+        out: &mut impl Write,
+    ) -> ClientResult<()> {
+        let request = self
+            .http
+            .get(self.url(&format!("/tasks/{}/logs", task_id)))
+            .query(options);
+        let mut response = self.send(request).await?;
+
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|e| ClientError::Connection(e.to_string()))?
+        {
+            out.write_all(&chunk)
+                .and_then(|_| out.flush())
+                .map_err(|e| ClientError::InvalidResponse(e.to_string()))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,33 @@
+use std::{error::Error, fmt};
+
+// * Client talks to a manager's API on behalf of the command line
+#[derive(Debug, Clone)]
+pub struct Client {
+    pub http: reqwest::Client,
+    // * host:port of the manager API
+    pub manager: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum ClientError {
+    Connection(String),
+    // * The manager answered with an error status
+    Rejected { status: u16, message: String },
+    InvalidResponse(String),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Connection(msg) => write!(f, "Connection failed: {}", msg),
+            ClientError::Rejected { status, message } => {
+                write!(f, "Manager returned status {}: {}", status, message)
+            }
+            ClientError::InvalidResponse(msg) => write!(f, "Invalid response: {}", msg),
+        }
+    }
+}
+
+impl Error for ClientError {}
+
+pub type ClientResult<T> = Result<T, ClientError>;
@@ -0,0 +1,547 @@
+use axum::{
+    Json, Router,
+    body::Body,
+    extract::{Path, RawQuery, State as AxumState},
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::{delete, get, post, put},
+};
+use futures_util::stream;
+
+use super::services::validate_template;
+use super::types::{
+    Manager, ManagerServer, ScaleRequest, ServiceSpec, ServiceUpdate, WorkerInfo,
+    WorkerRegistration,
+};
+use crate::lib::scheduler::types::Node;
+use crate::lib::tasks::{
+    manifest::validate_all,
+    state::valid_state_transition,
+    types::{EventKind, Manifest, State, TaskEvent},
+};
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::Mutex;
+
+impl ManagerServer {
+    pub fn new(manager: Arc<Mutex<Manager>>, address: &str, port: &str) -> Self {
+        Self {
+            manager,
+            address: address.to_string(),
+            port: port.to_string(),
+        }
+    }
+
+    async fn get_tasks(
+        AxumState(server): AxumState<Arc<Mutex<ManagerServer>>>,
+    ) -> impl IntoResponse {
+        let manager = server.lock().await.manager.clone();
+        let tasks = manager.lock().await.get_all_tasks();
+        match tasks {
+            Ok(tasks) => (StatusCode::OK, Json(tasks)).into_response(),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        }
+    }
+
+    async fn get_task(
+        AxumState(server): AxumState<Arc<Mutex<ManagerServer>>>,
+        Path(id): Path<String>,
+    ) -> impl IntoResponse {
+        let manager = server.lock().await.manager.clone();
+        match manager.lock().await.find_task(&id) {
+            Ok(Some(task)) => (StatusCode::OK, Json(task)).into_response(),
+            Ok(None) => (
+                StatusCode::NOT_FOUND,
+                format!("Task with id {} not found", id),
+            )
+                .into_response(),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        }
+    }
+
+    async fn start_task(
+        AxumState(server): AxumState<Arc<Mutex<ManagerServer>>>,
+        Json(task_event): Json<TaskEvent>,
+    ) -> impl IntoResponse {
+        if let Err(e) = task_event.validate() {
+            return (StatusCode::BAD_REQUEST, e).into_response();
+        }
+
+        let manager = server.lock().await.manager.clone();
+        let task = task_event.task.clone();
+        if let Err(e) = manager.lock().await.add_task(task_event.clone()) {
+            return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+        }
+        println!("Task Queued on manager: {:?}", task_event.task_id);
+        (StatusCode::CREATED, Json(task)).into_response()
+    }
+
+    async fn stop_task(
+        AxumState(server): AxumState<Arc<Mutex<ManagerServer>>>,
+        Path(id): Path<String>,
+    ) -> impl IntoResponse {
+        let manager = server.lock().await.manager.clone();
+        let mut guard = manager.lock().await;
+        let (task, queued) = match guard
+            .find_task(&id)
+            .and_then(|task| Ok((task, guard.is_queued(&id)?)))
+        {
+            Ok((Some(task), queued)) => (task, queued),
+            Ok((None, _)) => {
+                return (
+                    StatusCode::NOT_FOUND,
+                    format!("Task with id {} not found", id),
+                );
+            }
+            Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        };
+
+        // A task no worker has yet is taken out of the queue instead
+        if queued && valid_state_transition(&task.state, &State::Cancelled) {
+            return match guard.cancel_queued(task, "stop requested through the manager API") {
+                Ok(_) => {
+                    println!("Task cancelled on manager before it was placed: {:?}", id);
+                    (StatusCode::OK, format!("Task with id {} cancelled", id))
+                }
+                Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+            };
+        }
+
+        if !valid_state_transition(&task.state, &State::Stopping) {
+            return (
+                StatusCode::CONFLICT,
+                format!(
+                    "Task with id {} is {:?} and cannot be stopped",
+                    id, task.state
+                ),
+            );
+        }
+
+        let mut stopped_task = task;
+        stopped_task.transition(State::Stopping, "stop requested through the manager API");
+        stopped_task.next_generation();
+        if let Err(e) = guard.task_db.put(&id, stopped_task.clone()) {
+            return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+        }
+        if let Err(e) = guard.add_task(TaskEvent::new(EventKind::Stop, stopped_task)) {
+            return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+        }
+        println!("Task Queued on manager to stop: {:?}", id);
+        (
+            StatusCode::OK,
+            format!("Task with id {} queued to stop", id),
+        )
+    }
+
+    // * Creates or updates a task for every manifest. Nothing is applied when
+    // * any manifest is invalid; the response lists every error instead.
+    async fn apply(
+        AxumState(server): AxumState<Arc<Mutex<ManagerServer>>>,
+        Json(manifests): Json<Vec<Manifest>>,
+    ) -> impl IntoResponse {
+        let sources: Vec<String> = manifests
+            .iter()
+            .enumerate()
+            .map(|(i, manifest)| match manifest.name.as_str() {
+                "" => format!("manifest {}", i),
+                name => name.to_string(),
+            })
+            .collect();
+        let errors = validate_all(sources.iter().map(String::as_str).zip(&manifests));
+        if !errors.is_empty() {
+            let errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+            return (StatusCode::BAD_REQUEST, errors.join("\n")).into_response();
+        }
+
+        let manager = server.lock().await.manager.clone();
+        let results = manager.lock().await.apply(manifests);
+        match results {
+            Ok(results) => (StatusCode::OK, Json(results)).into_response(),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        }
+    }
+
+    async fn get_task_history(
+        AxumState(server): AxumState<Arc<Mutex<ManagerServer>>>,
+        Path(id): Path<String>,
+    ) -> impl IntoResponse {
+        let manager = server.lock().await.manager.clone();
+        match manager.lock().await.find_task(&id) {
+            Ok(Some(task)) => (StatusCode::OK, Json(task.history)).into_response(),
+            Ok(None) => (
+                StatusCode::NOT_FOUND,
+                format!("Task with id {} not found", id),
+            )
+                .into_response(),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        }
+    }
+
+    async fn get_task_logs(
+        AxumState(server): AxumState<Arc<Mutex<ManagerServer>>>,
+        Path(id): Path<String>,
+        RawQuery(query): RawQuery,
+        headers: HeaderMap,
+    ) -> Response {
+        let manager = server.lock().await.manager.clone();
+        let address = {
+            let manager = manager.lock().await;
+            match manager.get_task(&id) {
+                Ok(Some(_)) => manager.task_worker_address(&id),
+                Ok(None) => {
+                    return (
+                        StatusCode::NOT_FOUND,
+                        format!("Task with id {} not found", id),
+                    )
+                        .into_response();
+                }
+                Err(e) => Err(e),
+            }
+        };
+        let address = match address {
+            Ok(Some(address)) => address,
+            Ok(None) => {
+                return (
+                    StatusCode::CONFLICT,
+                    format!("Task with id {} is not placed on a worker", id),
+                )
+                    .into_response();
+            }
+            Err(e) => return (StatusCode::BAD_GATEWAY, e.to_string()).into_response(),
+        };
+
+        let accept = headers
+            .get(header::ACCEPT)
+            .and_then(|accept| accept.to_str().ok());
+        let response = match Manager::worker_logs(&address, &id, query.as_deref(), accept).await {
+            Ok(response) => response,
+            Err(e) => return (StatusCode::BAD_GATEWAY, e.to_string()).into_response(),
+        };
+
+        let status =
+            StatusCode::from_u16(response.status().as_u16()).unwrap_or(StatusCode::BAD_GATEWAY);
+        let content_type = response.headers().get(header::CONTENT_TYPE).cloned();
+        // Relays the chunks as they arrive so followed logs keep streaming
+        let body = stream::unfold(response, |mut response| async move {
+            match response.chunk().await {
+                Ok(Some(chunk)) => Some((Ok(chunk), response)),
+                Ok(None) => None,
+                Err(e) => Some((Err(e), response)),
+            }
+        });
+
+        let mut proxied = Body::from_stream(body).into_response();
+        *proxied.status_mut() = status;
+        if let Some(content_type) = content_type {
+            proxied
+                .headers_mut()
+                .insert(header::CONTENT_TYPE, content_type);
+        }
+        proxied
+    }
+
+    async fn get_workers(
+        AxumState(server): AxumState<Arc<Mutex<ManagerServer>>>,
+    ) -> Json<Vec<WorkerInfo>> {
+        let manager = server.lock().await.manager.clone();
+        let workers = manager.lock().await.get_workers();
+        Json(workers)
+    }
+
+    async fn get_nodes(AxumState(server): AxumState<Arc<Mutex<ManagerServer>>>) -> Json<Vec<Node>> {
+        let manager = server.lock().await.manager.clone();
+        let nodes = manager.lock().await.get_nodes();
+        Json(nodes)
+    }
+
+    async fn register_worker(
+        AxumState(server): AxumState<Arc<Mutex<ManagerServer>>>,
+        Json(registration): Json<WorkerRegistration>,
+    ) -> impl IntoResponse {
+        let manager = server.lock().await.manager.clone();
+        let info = manager.lock().await.register_worker(registration);
+        (StatusCode::CREATED, Json(info))
+    }
+
+    async fn heartbeat(
+        AxumState(server): AxumState<Arc<Mutex<ManagerServer>>>,
+        Path(name): Path<String>,
+    ) -> impl IntoResponse {
+        let manager = server.lock().await.manager.clone();
+        match manager.lock().await.heartbeat(&name) {
+            Ok(()) => (StatusCode::OK, format!("Heartbeat received from {}", name)),
+            Err(e) => (StatusCode::NOT_FOUND, e.to_string()),
+        }
+    }
+
+    async fn get_services(
+        AxumState(server): AxumState<Arc<Mutex<ManagerServer>>>,
+    ) -> impl IntoResponse {
+        let manager = server.lock().await.manager.clone();
+        let manager = manager.lock().await;
+        let statuses = manager
+            .service_db
+            .list()
+            .map_err(Into::into)
+            .and_then(|services| {
+                services
+                    .into_iter()
+                    .map(|service| manager.service_status(service))
+                    .collect::<Result<Vec<_>, _>>()
+            });
+        match statuses {
+            Ok(statuses) => (StatusCode::OK, Json(statuses)).into_response(),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        }
+    }
+
+    async fn create_service(
+        AxumState(server): AxumState<Arc<Mutex<ManagerServer>>>,
+        Json(spec): Json<ServiceSpec>,
+    ) -> impl IntoResponse {
+        if let Err(e) = spec.validate() {
+            return (StatusCode::BAD_REQUEST, e).into_response();
+        }
+
+        let manager = server.lock().await.manager.clone();
+        let mut manager = manager.lock().await;
+        match manager.find_service(&spec.name) {
+            Ok(Some(_)) => {
+                return (
+                    StatusCode::CONFLICT,
+                    format!("Service {} already exists", spec.name),
+                )
+                    .into_response();
+            }
+            Ok(None) => {}
+            Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        }
+        match manager.create_service(spec) {
+            Ok(service) => (StatusCode::CREATED, Json(service)).into_response(),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        }
+    }
+
+    async fn get_service(
+        AxumState(server): AxumState<Arc<Mutex<ManagerServer>>>,
+        Path(id): Path<String>,
+    ) -> impl IntoResponse {
+        let manager = server.lock().await.manager.clone();
+        let manager = manager.lock().await;
+        match manager.find_service(&id) {
+            Ok(Some(service)) => match manager.service_status(service) {
+                Ok(status) => (StatusCode::OK, Json(status)).into_response(),
+                Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+            },
+            Ok(None) => {
+                (StatusCode::NOT_FOUND, format!("Service {} not found", id)).into_response()
+            }
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        }
+    }
+
+    async fn scale_service(
+        AxumState(server): AxumState<Arc<Mutex<ManagerServer>>>,
+        Path(id): Path<String>,
+        Json(request): Json<ScaleRequest>,
+    ) -> impl IntoResponse {
+        let manager = server.lock().await.manager.clone();
+        let mut manager = manager.lock().await;
+        let scaled = match manager.find_service(&id) {
+            Ok(Some(service)) => manager
+                .scale_service(service, request.replicas)
+                .and_then(|service| manager.service_status(service)),
+            Ok(None) => {
+                return (StatusCode::NOT_FOUND, format!("Service {} not found", id))
+                    .into_response();
+            }
+            Err(e) => Err(e),
+        };
+        match scaled {
+            Ok(status) => (StatusCode::OK, Json(status)).into_response(),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        }
+    }
+
+    // * Rolls a new template out to the service's tasks
+    async fn update_service(
+        AxumState(server): AxumState<Arc<Mutex<ManagerServer>>>,
+        Path(id): Path<String>,
+        Json(update): Json<ServiceUpdate>,
+    ) -> impl IntoResponse {
+        let checked = validate_template(&id, &update.template).and_then(|_| {
+            update
+                .update_config
+                .as_ref()
+                .map_or(Ok(()), |config| config.validate())
+        });
+        if let Err(e) = checked {
+            return (StatusCode::BAD_REQUEST, e).into_response();
+        }
+
+        let manager = server.lock().await.manager.clone();
+        let mut manager = manager.lock().await;
+        let updated = match manager.find_service(&id) {
+            Ok(Some(service)) => manager
+                .update_service(
+                    service,
+                    update.template,
+                    update.update_config,
+                    "update requested through the manager API".to_string(),
+                )
+                .and_then(|service| manager.service_status(service)),
+            Ok(None) => {
+                return (StatusCode::NOT_FOUND, format!("Service {} not found", id))
+                    .into_response();
+            }
+            Err(e) => Err(e),
+        };
+        match updated {
+            Ok(status) => (StatusCode::OK, Json(status)).into_response(),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        }
+    }
+
+    // * Rolls the revision before the current one out again
+    async fn rollback_service(
+        AxumState(server): AxumState<Arc<Mutex<ManagerServer>>>,
+        Path(id): Path<String>,
+    ) -> impl IntoResponse {
+        let manager = server.lock().await.manager.clone();
+        let mut manager = manager.lock().await;
+        let rolled_back = match manager.find_service(&id) {
+            Ok(Some(service)) => manager.rollback_service(service),
+            Ok(None) => {
+                return (StatusCode::NOT_FOUND, format!("Service {} not found", id))
+                    .into_response();
+            }
+            Err(e) => Err(e),
+        };
+        match rolled_back.and_then(|service| service.map(|s| manager.service_status(s)).transpose())
+        {
+            Ok(Some(status)) => (StatusCode::OK, Json(status)).into_response(),
+            Ok(None) => (
+                StatusCode::CONFLICT,
+                format!(
+                    "Service {} has no earlier revision with a different template",
+                    id
+                ),
+            )
+                .into_response(),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        }
+    }
+
+    async fn resume_service(
+        AxumState(server): AxumState<Arc<Mutex<ManagerServer>>>,
+        Path(id): Path<String>,
+    ) -> impl IntoResponse {
+        let manager = server.lock().await.manager.clone();
+        let mut manager = manager.lock().await;
+        let resumed = match manager.find_service(&id) {
+            Ok(Some(service)) => manager.resume_service(service),
+            Ok(None) => {
+                return (StatusCode::NOT_FOUND, format!("Service {} not found", id))
+                    .into_response();
+            }
+            Err(e) => Err(e),
+        };
+        match resumed.and_then(|service| service.map(|s| manager.service_status(s)).transpose()) {
+            Ok(Some(status)) => (StatusCode::OK, Json(status)).into_response(),
+            Ok(None) => (
+                StatusCode::CONFLICT,
+                format!("Service {} has no paused rollout", id),
+            )
+                .into_response(),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        }
+    }
+
+    async fn delete_service(
+        AxumState(server): AxumState<Arc<Mutex<ManagerServer>>>,
+        Path(id): Path<String>,
+    ) -> impl IntoResponse {
+        let manager = server.lock().await.manager.clone();
+        let mut manager = manager.lock().await;
+        let deleted = match manager.find_service(&id) {
+            Ok(Some(service)) => manager.delete_service(service),
+            Ok(None) => return (StatusCode::NOT_FOUND, format!("Service {} not found", id)),
+            Err(e) => Err(e),
+        };
+        match deleted {
+            Ok(()) => (StatusCode::OK, format!("Service {} deleted", id)),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        }
+    }
+
+    pub async fn start_server(self) {
+        let address = self.address.clone();
+        let port = self.port.clone();
+        let shared = Arc::new(Mutex::new(self));
+        println!("Starting ManagerServer at {}:{}", address, port);
+
+        let app = Router::new()
+            .route("/tasks", get(ManagerServer::get_tasks))
+            .route("/tasks", post(ManagerServer::start_task))
+            .route("/tasks/{id}", get(ManagerServer::get_task))
+            .route("/tasks/{id}", delete(ManagerServer::stop_task))
+            .route("/tasks/{id}/history", get(ManagerServer::get_task_history))
+            .route("/tasks/{id}/logs", get(ManagerServer::get_task_logs))
+            .route("/apply", post(ManagerServer::apply))
+            .route("/services", get(ManagerServer::get_services))
+            .route("/services", post(ManagerServer::create_service))
+            .route("/services/{id}", get(ManagerServer::get_service))
+            .route("/services/{id}", delete(ManagerServer::delete_service))
+            .route("/services/{id}/scale", put(ManagerServer::scale_service))
+            .route("/services/{id}/update", post(ManagerServer::update_service))
+            .route(
+                "/services/{id}/rollback",
+                post(ManagerServer::rollback_service),
+            )
+            .route("/services/{id}/resume", post(ManagerServer::resume_service))
+            .route("/nodes", get(ManagerServer::get_nodes))
+            .route("/workers", get(ManagerServer::get_workers))
+            .route("/workers", post(ManagerServer::register_worker))
+            .route("/workers/{name}/heartbeat", post(ManagerServer::heartbeat))
+            .with_state(shared);
+
+        println!("Listening on {}:{}", address, port);
+        let listener = TcpListener::bind(format!("{}:{}", address, port))
+            .await
+            .unwrap();
+
+        axum::serve(listener, app).await.unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lib::{scheduler::types::SchedulerType, tasks::types::Task};
+
+    #[tokio::test]
+    async fn rolling_back_without_an_earlier_template_is_a_conflict() {
+        let mut manager = Manager::new(Vec::new(), SchedulerType::RoundRobin);
+        let service = manager
+            .create_service(ServiceSpec {
+                name: "web".to_string(),
+                replicas: 1,
+                template: Task {
+                    image: "web:1".to_string(),
+                    ..Task::default()
+                },
+                update_config: Default::default(),
+            })
+            .unwrap();
+        let server = ManagerServer::new(Arc::new(Mutex::new(manager)), "127.0.0.1", "0");
+
+        let response = ManagerServer::rollback_service(
+            AxumState(Arc::new(Mutex::new(server))),
+            Path(service.name),
+        )
+        .await
+        .into_response();
+
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+    }
+}
@@ -0,0 +1,95 @@
+use std::fmt;
+
+use super::types::{ApplyAction, ApplyResult, Manager, ManagerResult};
+use crate::lib::tasks::types::{EventKind, Manifest, Task, TaskEvent};
+
+impl fmt::Display for ApplyAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApplyAction::Created => write!(f, "created"),
+            ApplyAction::Updated => write!(f, "updated"),
+            ApplyAction::Unchanged => write!(f, "unchanged"),
+        }
+    }
+}
+
+impl Manager {
+    // * The live task a manifest with this name manages: a task the manager
+    // * tracks that has not finished, or one whose start is still queued
+    fn live_task(&self, name: &str) -> ManagerResult<Option<Task>> {
+        let tracked = self
+            .task_db
+            .list()?
+            .into_iter()
+            .find(|task| task.name == name && !task.state.is_terminal());
+        if tracked.is_some() {
+            return Ok(tracked);
+        }
+
+        Ok(self
+            .pending
+            .iter()
+            .find(|event| event.kind == EventKind::Start && event.task.name == name)
+            .map(|event| event.task.clone()))
+    }
+
+    // * Creates a task for every manifest without a live task of its name
+    // * and updates the live tasks whose spec differs from their manifest.
+    // * The manifests are expected to be validated together beforehand.
+    pub fn apply(&mut self, manifests: Vec<Manifest>) -> ManagerResult<Vec<ApplyResult>> {
+        let mut results = Vec::with_capacity(manifests.len());
+
+        for manifest in manifests {
+            let desired = manifest.to_task();
+
+            let Some(task) = self.live_task(&manifest.name)? else {
+                println!(
+                    "Applying manifest {}: new task {}",
+                    desired.name, desired.id
+                );
+                results.push(ApplyResult {
+                    name: manifest.name,
+                    task_id: desired.id.clone(),
+                    action: ApplyAction::Created,
+                });
+                self.add_task(TaskEvent::new(EventKind::Start, desired))?;
+                continue;
+            };
+
+            if task.same_spec(&desired) {
+                results.push(ApplyResult {
+                    name: manifest.name,
+                    task_id: task.id,
+                    action: ApplyAction::Unchanged,
+                });
+                continue;
+            }
+
+            println!("Applying manifest {}: updating task {}", task.name, task.id);
+            // Events that have not been sent yet carry the new spec along
+            for event in self.pending.iter_mut() {
+                if event.task.id == task.id {
+                    event.task = event.task.with_spec(&desired);
+                }
+            }
+            self.save_pending()?;
+            // A task on a worker gets its container replaced, one waiting to
+            // be placed only needs the new spec
+            let mut updated = task.with_spec(&desired);
+            updated.next_generation();
+            if self.task_worker_hash_map.get(&task.id)?.is_some() {
+                self.add_task(TaskEvent::new(EventKind::Update, updated))?;
+            } else if self.task_db.get(&task.id)?.is_some() {
+                self.task_db.put(&task.id, updated)?;
+            }
+
+            results.push(ApplyResult {
+                name: manifest.name,
+                task_id: task.id,
+                action: ApplyAction::Updated,
+            });
+        }
+
+        Ok(results)
+    }
+}
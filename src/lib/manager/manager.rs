@@ -1,80 +1,390 @@
-use crate::lib::manager::types::{ManagerError, ManagerResult};
-use crate::lib::tasks::types::Task;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use futures_util::future::join_all;
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+
+use crate::lib::manager::types::{
+    Dispatch, ManagerError, ManagerIntervals, ManagerResult, ManagerStores, WorkerCapacity,
+    WorkerInfo, WorkerStatus,
+};
+use crate::lib::scheduler::{
+    scheduler::{new_scheduler, satisfies_constraints},
+    types::{Node, SchedulerType},
+};
+use crate::lib::tasks::types::{EventKind, State, Task};
 use crate::lib::{manager::types::Manager, tasks::types::TaskEvent};
 
+// * Key the pending queue is stored under
+const PENDING_KEY: &str = "pending";
+
+// * Limits on requests to workers
+const WORKER_CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+const WORKER_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+// * Client for requests to workers. Following logs has no overall timeout,
+// * so it is left out of the request one.
+fn worker_client(request_timeout: Option<Duration>) -> reqwest::Client {
+    let mut builder = reqwest::Client::builder().connect_timeout(WORKER_CONNECT_TIMEOUT);
+    if let Some(timeout) = request_timeout {
+        builder = builder.timeout(timeout);
+    }
+    builder.build().unwrap_or_default()
+}
+
 impl Manager {
-    pub fn new(workers: Vec<String>) -> Self {
+    pub fn new(workers: Vec<String>, scheduler_type: SchedulerType) -> Self {
+        Self::with_stores(workers, scheduler_type, ManagerStores::default())
+    }
+
+    pub fn with_stores(
+        workers: Vec<String>,
+        scheduler_type: SchedulerType,
+        stores: ManagerStores,
+    ) -> Self {
+        let nodes = workers
+            .iter()
+            .map(|worker| Node::new(worker, worker, "worker"))
+            .collect();
+
+        // Statically configured workers are named after their address
+        let workers = workers
+            .into_iter()
+            .map(|address| {
+                let info = WorkerInfo {
+                    name: address.clone(),
+                    address: address.clone(),
+                    capacity: WorkerCapacity::default(),
+                    status: WorkerStatus::Healthy,
+                    last_heartbeat: None,
+                    failures: 0,
+                    labels: HashMap::new(),
+                };
+                (address, info)
+            })
+            .collect();
+
+        let pending = match stores.pending.get(PENDING_KEY) {
+            Ok(pending) => pending.unwrap_or_default(),
+            Err(e) => {
+                eprintln!("Error loading the pending queue: {}", e);
+                Default::default()
+            }
+        };
+
         Manager {
             workers,
-            last_worker: 0,
-            pending: std::collections::VecDeque::new(),
-            task_db: std::collections::HashMap::new(),
-            event_db: std::collections::HashMap::new(),
-            worker_task_hash_map: std::collections::HashMap::new(),
-            task_worker_hash_map: std::collections::HashMap::new(),
+            nodes,
+            scheduler: new_scheduler(scheduler_type),
+            pending,
+            pending_db: stores.pending,
+            task_db: stores.tasks,
+            event_db: stores.events,
+            worker_task_hash_map: stores.worker_tasks,
+            task_worker_hash_map: stores.task_workers,
+            service_db: stores.services,
+            http: worker_client(Some(WORKER_REQUEST_TIMEOUT)),
         }
     }
 
-    pub fn select_worker(&mut self) -> ManagerResult<String> {
-        if self.workers.is_empty() {
+    fn healthy_nodes(&self) -> Vec<Node> {
+        self.nodes
+            .iter()
+            .filter(|node| {
+                self.workers
+                    .get(&node.name)
+                    .is_some_and(|worker| worker.status == WorkerStatus::Healthy)
+            })
+            .cloned()
+            .collect()
+    }
+
+    pub fn select_worker(&self, task: &Task) -> ManagerResult<String> {
+        let healthy = self.healthy_nodes();
+        let candidates = self.scheduler.select_candidate_nodes(task, &healthy);
+        if candidates.is_empty() {
             return Err(ManagerError::NoWorkersAvailable);
         }
 
-        let new_worker = if self.last_worker + 1 < self.workers.len() as u16 {
-            self.last_worker + 1
+        let scores = self.scheduler.score(task, &candidates);
+        self.scheduler
+            .pick(&scores, &candidates)
+            .map(|node| node.name)
+            .ok_or(ManagerError::NoWorkersAvailable)
+    }
+
+    // * Why `select_worker` finds no worker for the task
+    fn unplaceable_reason(&self, task: &Task) -> String {
+        let healthy = self.healthy_nodes();
+        if healthy.is_empty() {
+            "no healthy worker".to_string()
+        } else if !healthy.iter().any(|node| satisfies_constraints(task, node)) {
+            "no worker matches its placement rules".to_string()
         } else {
-            self.last_worker = 0;
-            0
+            "no worker has room for it".to_string()
+        }
+    }
+
+    // * Polls every worker for its tasks, including unhealthy and down ones so
+    // * a worker that comes back is noticed. A worker that fails
+    // * `failure_threshold` polls in a row is marked down and its tasks are
+    // * placed elsewhere. The manager is only locked to snapshot the workers
+    // * and to apply what they reported, not while they are polled.
+    pub async fn update_tasks(
+        manager: &Mutex<Manager>,
+        failure_threshold: u32,
+    ) -> ManagerResult<()> {
+        let (workers, http) = {
+            let manager = manager.lock().await;
+            (manager.get_workers(), manager.http.clone())
         };
+        let polls = join_all(
+            workers
+                .iter()
+                .map(|worker| Manager::get_worker_tasks(&http, &worker.address)),
+        )
+        .await;
 
-        self.last_worker = new_worker;
-        Ok(self.workers[new_worker as usize].clone())
+        let mut moved = Vec::new();
+        {
+            let mut manager = manager.lock().await;
+            for (worker, poll) in workers.iter().zip(polls) {
+                for task_id in manager.apply_worker_tasks(worker, poll, failure_threshold)? {
+                    moved.push((worker.clone(), task_id));
+                }
+            }
+            manager.refresh_allocations();
+        }
+
+        // A task moved off a worker while it was down must not keep running
+        // there once the worker is back
+        for (worker, task_id) in moved {
+            println!(
+                "Task {} was moved off worker {}, stopping the old copy",
+                task_id, worker.name
+            );
+            if let Err(e) = Manager::stop_worker_task(&http, &worker.address, &task_id).await {
+                eprintln!("Error stopping moved task {}: {:?}", task_id, e);
+            }
+        }
+        Ok(())
     }
 
-    pub async fn update_task(&mut self) -> ManagerResult<()> {
-        for worker in &self.workers {
-            println!("Checking worker: {}", worker);
+    // * Records the outcome of polling `worker` for its tasks. Returns the
+    // * running tasks it reported that were moved to another worker since.
+    fn apply_worker_tasks(
+        &mut self,
+        worker: &WorkerInfo,
+        poll: ManagerResult<Vec<Task>>,
+        failure_threshold: u32,
+    ) -> ManagerResult<Vec<String>> {
+        // The worker may have expired while it was polled
+        if !self.workers.contains_key(&worker.name) {
+            return Ok(Vec::new());
+        }
+
+        let tasks = match poll {
+            Ok(tasks) => {
+                self.worker_reachable(&worker.name);
+                tasks
+            }
+            Err(e) => {
+                eprintln!("Error polling worker {}: {}", worker.name, e);
+                if self.worker_unreachable(&worker.name, failure_threshold)
+                    && let Err(e) = self.reschedule_worker_tasks(&worker.name)
+                {
+                    eprintln!(
+                        "Error rescheduling tasks of worker {}: {:?}",
+                        worker.name, e
+                    );
+                }
+                return Ok(Vec::new());
+            }
+        };
+
+        let now = SystemTime::now();
+        let mut moved = Vec::new();
+        for task in &tasks {
+            // A restarted task may have moved on from this worker
+            if self.task_worker_hash_map.get(&task.id)?.as_ref() != Some(&worker.name) {
+                if task.state == State::Running && self.task_db.get(&task.id)?.is_some() {
+                    moved.push(task.id.clone());
+                }
+                continue;
+            }
 
-            let tasks = self.get_worker_tasks(worker.clone()).await?;
-            for task in tasks {
-                if let Some(_) = self.event_db.get(&task.id) {
-                    println!("Attempting to update task: {}", task.id);
+            if let Some(local_task) = self.task_db.get(&task.id)? {
+                println!("Attempting to update task: {}", task.id);
 
-                    if self.task_db.contains_key(&task.id) {
-                        if let Some(local_task) = self.task_db.get(&task.id) {
-                            let new_task = Task {
-                                container_id: task.container_id.clone(),
-                                start_time: task.start_time,
-                                finish_time: task.finish_time,
-                                state: task.state.clone(),
-                                ..local_task.clone()
-                            };
+                // A change the manager made since, like a requested stop,
+                // wins over what the worker reported before it saw the change
+                if task.generation < local_task.generation {
+                    println!(
+                        "Ignoring stale report of task {} (generation {} < {})",
+                        task.id, task.generation, local_task.generation
+                    );
+                    continue;
+                }
 
-                            if local_task.state != task.state {
-                                self.task_db.insert(task.id.clone(), new_task);
-                            }
-                        }
-                    }
+                // The worker's start_time is on its own clock and set before
+                // the image pull, so the manager notes when it saw the run
+                let same_run =
+                    local_task.state == State::Running && local_task.start_time == task.start_time;
+                let running_since = match task.state {
+                    State::Running if same_run => local_task.running_since.or(Some(now)),
+                    State::Running => Some(now),
+                    _ => None,
+                };
+
+                let mut new_task = Task {
+                    container_id: task.container_id.clone(),
+                    assigned_ports: task.assigned_ports.clone(),
+                    exit_code: task.exit_code,
+                    health: task.health,
+                    health_failures: task.health_failures,
+                    pull_events: task.pull_events.clone(),
+                    start_time: task.start_time,
+                    finish_time: task.finish_time,
+                    state: task.state.clone(),
+                    generation: task.generation,
+                    running_since,
+                    ..local_task.clone()
+                };
+                new_task.merge_history(&task.history);
+
+                if local_task.state != task.state
+                    || local_task.health != task.health
+                    || local_task.pull_events != task.pull_events
+                    || local_task.running_since != running_since
+                {
+                    self.task_db.put(&task.id, new_task)?;
                 }
             }
         }
 
-        Ok(())
+        // Tasks the worker was running but no longer reports
+        let assigned = self
+            .worker_task_hash_map
+            .get(&worker.name)?
+            .unwrap_or_default();
+        for task_id in assigned {
+            if tasks.iter().any(|task| task.id == task_id) {
+                continue;
+            }
+            let Some(mut local_task) = self.task_db.get(&task_id)? else {
+                continue;
+            };
+            if matches!(local_task.state, State::Running | State::Stopping) {
+                local_task.transition(
+                    State::Unknown,
+                    format!("no longer reported by worker {}", worker.name),
+                );
+                self.task_db.put(&task_id, local_task)?;
+            }
+        }
+
+        Ok(moved)
+    }
+
+    pub fn add_task(&mut self, task_event: TaskEvent) -> ManagerResult<()> {
+        self.pending.push_back(task_event);
+        self.save_pending()
     }
 
-    pub fn add_task(&mut self, task_event: TaskEvent) {
-        self.pending.push_back(task_event.clone());
+    // * Writes the pending queue through to its store. Called after every
+    // * change to the queue.
+    pub fn save_pending(&self) -> ManagerResult<()> {
+        Ok(self.pending_db.put(PENDING_KEY, self.pending.clone())?)
+    }
+
+    // * Every task, including the ones whose start is still queued
+    pub fn get_all_tasks(&self) -> ManagerResult<Vec<Task>> {
+        let mut tasks = self.task_db.list()?;
+        for event in &self.pending {
+            if event.kind == EventKind::Start && !tasks.iter().any(|task| task.id == event.task.id)
+            {
+                tasks.push(event.task.clone());
+            }
+        }
+        Ok(tasks)
     }
 
-    pub fn get_all_tasks(&self) -> Vec<Task> {
-        self.task_db.values().cloned().collect()
+    // * Looks a task up in the store, then among the queued starts, so a task
+    // * that waits for a worker shows why in its history
+    pub fn find_task(&self, task_id: &str) -> ManagerResult<Option<Task>> {
+        if let Some(task) = self.task_db.get(task_id)? {
+            return Ok(Some(task));
+        }
+        Ok(self
+            .pending
+            .iter()
+            .find(|event| event.kind == EventKind::Start && event.task.id == task_id)
+            .map(|event| event.task.clone()))
+    }
+
+    pub fn get_task(&self, task_id: &str) -> ManagerResult<Option<Task>> {
+        Ok(self.task_db.get(task_id)?)
+    }
+
+    // * Whether the task waits in the queue without a worker, so stopping it
+    // * means cancelling it there
+    pub fn is_queued(&self, task_id: &str) -> ManagerResult<bool> {
+        Ok(self.task_worker_hash_map.get(task_id)?.is_none()
+            && self.pending.iter().any(|event| event.task.id == task_id))
+    }
+
+    // * Drops the queued events of a task that was never placed and records
+    // * it as cancelled
+    pub fn cancel_queued(&mut self, mut task: Task, reason: &str) -> ManagerResult<Task> {
+        self.pending.retain(|event| event.task.id != task.id);
+        self.save_pending()?;
+        task.transition(State::Cancelled, reason);
+        task.next_generation();
+        task.finish_time = Some(SystemTime::now());
+        self.task_db.put(&task.id, task.clone())?;
+        Ok(task)
+    }
+
+    // * Address of the worker the task is placed on, if it is placed at all
+    pub fn task_worker_address(&self, task_id: &str) -> ManagerResult<Option<String>> {
+        match self.task_worker_hash_map.get(task_id)? {
+            Some(worker) => Ok(Some(self.worker_address(&worker)?)),
+            None => Ok(None),
+        }
+    }
+
+    // * Opens the log stream of a task on its worker. Takes no manager so the
+    // * lock isn't held for as long as the logs are followed
+    pub async fn worker_logs(
+        address: &str,
+        task_id: &str,
+        query: Option<&str>,
+        accept: Option<&str>,
+    ) -> ManagerResult<reqwest::Response> {
+        let mut url = format!("http://{}/tasks/{}/logs", address, task_id);
+        if let Some(query) = query {
+            url.push('?');
+            url.push_str(query);
+        }
+
+        let mut request = worker_client(None).get(&url);
+        if let Some(accept) = accept {
+            request = request.header("Accept", accept);
+        }
+        request
+            .send()
+            .await
+            .map_err(|_| ManagerError::NetworkError(format!("Failed to connect to {}", url)))
     }
 
-    async fn get_worker_tasks(&self, worker: String) -> ManagerResult<Vec<Task>> {
-        let url = format!("http://{}/tasks/", worker);
+    async fn get_worker_tasks(http: &reqwest::Client, worker: &str) -> ManagerResult<Vec<Task>> {
+        let url = format!("http://{}/tasks", worker);
 
-        let client = reqwest::Client::new();
-        let resp = client
+        let resp = http
             .get(&url)
             .send()
             .await
@@ -98,14 +408,17 @@ impl Manager {
         }
     }
 
-    async fn send_worker_event(&self, worker: String, task_event: TaskEvent) -> ManagerResult<()> {
+    async fn send_worker_event(
+        http: &reqwest::Client,
+        worker: &str,
+        task_event: &TaskEvent,
+    ) -> ManagerResult<()> {
         let url = format!("http://{}/tasks", worker);
 
-        let client = reqwest::Client::new();
-        let response = client
+        let response = http
             .post(&url)
             .header("Content-Type", "application/json")
-            .json(&task_event)
+            .json(task_event)
             .send()
             .await
             .map_err(|_| ManagerError::NetworkError(format!("Failed to connect to {}", url)))?;
@@ -120,39 +433,337 @@ impl Manager {
         }
     }
 
-    pub async fn send_work(&mut self) -> ManagerResult<()> {
-        if !self.pending.is_empty() {
-            let worker = self.select_worker()?;
+    async fn stop_worker_task(
+        http: &reqwest::Client,
+        worker: &str,
+        task_id: &str,
+    ) -> ManagerResult<()> {
+        let url = format!("http://{}/tasks/{}", worker, task_id);
+
+        let response = http
+            .delete(&url)
+            .send()
+            .await
+            .map_err(|_| ManagerError::NetworkError(format!("Failed to connect to {}", url)))?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(ManagerError::WorkerCommunication(format!(
+                "Worker {} failed to stop task {}: status {}",
+                worker,
+                task_id,
+                response.status().as_u16()
+            )))
+        }
+    }
+
+    // * Works out where a pending event goes and records the placement of a
+    // * task it starts. Returns None for an event there is nothing to send
+    // * for.
+    fn dispatch(&mut self, task_event: TaskEvent) -> ManagerResult<Option<Dispatch>> {
+        self.event_db.put(&task_event.task_id, task_event.clone())?;
+
+        // Events for a task that already lives on a worker go to that
+        // worker; only starts and restarts of unplaced tasks pick one
+        match (
+            &task_event.kind,
+            self.task_worker_hash_map.get(&task_event.task.id)?,
+        ) {
+            (EventKind::Start, Some(worker)) => {
+                println!(
+                    "Task {} is already assigned to worker {}, ignoring event",
+                    task_event.task.id, worker
+                );
+                return Ok(None);
+            }
+            (
+                EventKind::Stop | EventKind::Restart | EventKind::Update | EventKind::Signal { .. },
+                Some(worker),
+            ) => {
+                if task_event.kind == EventKind::Update
+                    && let Some(local_task) = self.task_db.get(&task_event.task.id)?
+                {
+                    let mut updated = local_task.with_spec(&task_event.task);
+                    updated.generation = updated.generation.max(task_event.task.generation);
+                    self.task_db.put(&local_task.id, updated)?;
+                }
 
-            let task_event = self.pending.pop_back().unwrap();
+                return Ok(Some(Dispatch {
+                    address: self.worker_address(&worker)?,
+                    worker,
+                    event: task_event,
+                }));
+            }
+            (EventKind::Start | EventKind::Restart, None) => {}
+            (kind, None) => {
+                println!(
+                    "Task {} is not on any worker, ignoring {} event",
+                    task_event.task.id, kind
+                );
+                return Ok(None);
+            }
+        }
+
+        let selected = self.select_worker(&task_event.task).and_then(|worker| {
+            let address = self.worker_address(&worker)?;
+            Ok((worker, address))
+        });
+
+        let (worker, address) = selected?;
+
+        let mut worker_tasks = self.worker_task_hash_map.get(&worker)?.unwrap_or_default();
+        worker_tasks.push(task_event.task.id.clone());
+        self.worker_task_hash_map.put(&worker, worker_tasks)?;
+
+        self.task_worker_hash_map
+            .put(&task_event.task.id, worker.clone())?;
+
+        let mut task_event = task_event;
+        task_event
+            .task
+            .transition(State::Scheduled, format!("placed on worker {}", worker));
+        self.task_db
+            .put(&task_event.task.id, task_event.task.clone())?;
+        self.refresh_allocations();
+
+        Ok(Some(Dispatch {
+            worker,
+            address,
+            event: task_event,
+        }))
+    }
+
+    // * Takes back the placement a dispatch made for an event that did not
+    // * reach its worker: the task is unassigned and its stored copy goes back
+    // * to what it was before, so the event can be placed again
+    fn undo_placement(&mut self, task_id: &str, previous: Option<Task>) -> ManagerResult<()> {
+        self.unassign(task_id)?;
+        match previous {
+            Some(task) => self.task_db.put(task_id, task)?,
+            None => self.task_db.delete(task_id)?,
+        }
+        self.refresh_allocations();
+        Ok(())
+    }
+
+    // * Drains every pending event, stopping at the first failure. An event
+    // * only leaves the queue once its worker accepted it, so one that cannot
+    // * be sent yet is retried on the next tick. Events no worker can take
+    // * right now are skipped instead, so a task whose placement rules no
+    // * worker meets does not hold up the ones behind it. The manager is not
+    // * locked while an event is sent.
+    async fn send_pending_work(manager: &Mutex<Manager>) {
+        let http = manager.lock().await.http.clone();
+        let mut unplaced = HashSet::new();
+        loop {
+            let (dispatch, previous, was_placed) = {
+                let mut manager = manager.lock().await;
+                let Some(index) = manager
+                    .pending
+                    .iter()
+                    .position(|event| !unplaced.contains(&event.task.id))
+                else {
+                    break;
+                };
+                let task_event = manager.pending[index].clone();
+                let task_id = task_event.task.id.clone();
+                let snapshot = manager.task_db.get(&task_id).and_then(|previous| {
+                    Ok((
+                        previous,
+                        manager.task_worker_hash_map.get(&task_id)?.is_some(),
+                    ))
+                });
+                let (previous, was_placed) = match snapshot {
+                    Ok(snapshot) => snapshot,
+                    Err(e) => {
+                        eprintln!("Error sending work: {}", e);
+                        break;
+                    }
+                };
+                let dispatch = manager.dispatch(task_event);
+                if let Err(ManagerError::NoWorkersAvailable) = dispatch {
+                    let reason = format!(
+                        "waiting for a worker: {}",
+                        manager.unplaceable_reason(&manager.pending[index].task)
+                    );
+                    println!("Task {} is {}", task_id, reason);
+                    // Recorded once, not on every tick it keeps waiting
+                    let task = &mut manager.pending[index].task;
+                    if task.history.last().is_none_or(|last| last.reason != reason) {
+                        let state = task.state.clone();
+                        if task.transition(state, reason)
+                            && let Err(e) = manager.save_pending()
+                        {
+                            eprintln!("Error saving the pending queue: {:?}", e);
+                        }
+                    }
+                    unplaced.insert(task_id);
+                    continue;
+                }
 
-            self.event_db
-                .insert(task_event.task_id.clone(), task_event.clone());
+                match dispatch {
+                    Ok(Some(dispatch)) => (dispatch, previous, was_placed),
+                    Ok(None) => {
+                        manager.pending.remove(index);
+                        if let Err(e) = manager.save_pending() {
+                            eprintln!("Error saving the pending queue: {:?}", e);
+                        }
+                        continue;
+                    }
+                    Err(e) => {
+                        eprintln!("Error sending work: {:?}", e);
+                        if !was_placed && let Err(e) = manager.undo_placement(&task_id, previous) {
+                            eprintln!("Error undoing the placement of {}: {:?}", task_id, e);
+                        }
+                        break;
+                    }
+                }
+            };
 
-            self.worker_task_hash_map
-                .entry(worker.clone())
-                .or_default()
-                .push(task_event.task_id.clone());
+            let Dispatch {
+                worker,
+                address,
+                event,
+            } = dispatch;
+            // Stops go out as events too, so the worker learns the task's
+            // generation along with them
+            let sent = Manager::send_worker_event(&http, &address, &event).await;
 
-            self.task_worker_hash_map
-                .insert(task_event.task_id.clone(), worker.clone());
+            let mut manager = manager.lock().await;
+            if let Err(e) = sent {
+                eprintln!("Error sending {} event: {:?}", event.kind, e);
+                if !was_placed && let Err(e) = manager.undo_placement(&event.task.id, previous) {
+                    eprintln!("Error undoing the placement of {}: {:?}", event.task.id, e);
+                }
+                break;
+            }
+            println!("Sent {} event to worker {}", event.kind, worker);
+            // The queue may have changed while the event was sent, like a
+            // retired task taking its events with it
+            if let Some(index) = manager.pending.iter().position(|pending| {
+                pending.task_id == event.task_id && pending.task.id == event.task.id
+            }) {
+                manager.pending.remove(index);
+                if let Err(e) = manager.save_pending() {
+                    eprintln!("Error saving the pending queue: {:?}", e);
+                }
+            }
+        }
+    }
 
-            self.task_db
-                .insert(task_event.task_id.clone(), task_event.task.clone());
+    pub async fn run(
+        manager: Arc<Mutex<Manager>>,
+        intervals: ManagerIntervals,
+        cancel: CancellationToken,
+    ) {
+        let mut send_work = tokio::time::interval(intervals.send_work);
+        let mut update_tasks = tokio::time::interval(intervals.update_tasks);
+        let mut check_workers = tokio::time::interval(intervals.check_workers);
+        let mut update_nodes = tokio::time::interval(intervals.update_nodes);
+        let mut restart_tasks = tokio::time::interval(intervals.restart_tasks);
+        let mut reconcile_services = tokio::time::interval(intervals.reconcile_services);
 
-            match self.send_worker_event(worker, task_event).await {
-                Ok(_) => {
-                    println!("Event sent successfully");
-                    Ok(())
+        loop {
+            tokio::select! {
+                _ = cancel.cancelled() => {
+                    println!("Manager reconciliation loop shutting down");
+                    break;
                 }
-                Err(e) => {
-                    eprintln!("Error sending event: {:?}", e);
-                    Err(e)
+                _ = send_work.tick() => {
+                    Manager::send_pending_work(&manager).await;
+                }
+                _ = check_workers.tick() => {
+                    manager
+                        .lock()
+                        .await
+                        .check_workers(intervals.heartbeat_timeout, intervals.worker_expiry);
+                }
+                _ = update_nodes.tick() => {
+                    Manager::update_nodes(&manager).await;
+                }
+                _ = restart_tasks.tick() => {
+                    let restarted = manager
+                        .lock()
+                        .await
+                        .restart_tasks(intervals.restart_backoff, intervals.max_restart_backoff);
+                    if let Err(e) = restarted {
+                        eprintln!("Error restarting tasks: {:?}", e);
+                    }
+                }
+                _ = reconcile_services.tick() => {
+                    if let Err(e) = manager.lock().await.reconcile_services() {
+                        eprintln!("Error reconciling services: {:?}", e);
+                    }
+                }
+                _ = update_tasks.tick() => {
+                    println!("Checking for task updates from workers");
+                    let update =
+                        Manager::update_tasks(&manager, intervals.worker_failure_threshold).await;
+                    if let Err(e) = update {
+                        eprintln!("Error updating tasks: {:?}", e);
+                    }
                 }
             }
-        } else {
-            println!("No pending tasks to send");
-            Ok(())
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn an_event_the_worker_never_got_stays_queued_and_unplaced() {
+        // Nothing listens on port 1, so every send fails
+        let manager = Mutex::new(Manager::new(
+            vec!["127.0.0.1:1".to_string()],
+            SchedulerType::RoundRobin,
+        ));
+        let task = Task::default();
+        manager
+            .lock()
+            .await
+            .add_task(TaskEvent::new(EventKind::Start, task.clone()))
+            .unwrap();
+
+        Manager::send_pending_work(&manager).await;
+
+        let manager = manager.lock().await;
+        assert_eq!(manager.pending.len(), 1);
+        assert_eq!(manager.pending[0].task.state, State::Pending);
+        assert_eq!(manager.task_worker_hash_map.get(&task.id).unwrap(), None);
+        assert!(manager.task_db.get(&task.id).unwrap().is_none());
+        assert_eq!(
+            manager.pending_db.get(PENDING_KEY).unwrap().unwrap().len(),
+            1
+        );
+        assert_eq!(manager.nodes[0].task_count, 0);
+    }
+
+    #[test]
+    fn a_queued_task_is_cancelled_out_of_the_queue() {
+        let mut manager = Manager::new(Vec::new(), SchedulerType::RoundRobin);
+        let task = Task::default();
+        manager
+            .add_task(TaskEvent::new(EventKind::Start, task.clone()))
+            .unwrap();
+
+        let queued = manager.find_task(&task.id).unwrap().unwrap();
+        assert!(manager.is_queued(&task.id).unwrap());
+        manager.cancel_queued(queued, "stopped").unwrap();
+
+        assert!(manager.pending.is_empty());
+        assert!(
+            manager
+                .pending_db
+                .get(PENDING_KEY)
+                .unwrap()
+                .unwrap()
+                .is_empty()
+        );
+        let cancelled = manager.get_task(&task.id).unwrap().unwrap();
+        assert_eq!(cancelled.state, State::Cancelled);
+        assert!(!manager.is_queued(&task.id).unwrap());
+    }
+}
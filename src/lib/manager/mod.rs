@@ -1,3 +1,10 @@
-// pub mod api;
+pub mod api;
+pub mod apply;
+#[allow(clippy::module_inception)]
 pub mod manager;
+pub mod nodes;
+pub mod registry;
+pub mod restart;
+pub mod rollout;
+pub mod services;
 pub mod types;
@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+
+use futures_util::future::join_all;
+use tokio::sync::Mutex;
+
+use super::types::{Manager, ManagerError, ManagerResult};
+use crate::lib::{scheduler::types::Node, worker::types::SystemStats};
+
+impl Node {
+    pub fn apply_stats(&mut self, stats: &SystemStats) {
+        self.cores = stats.total_cpus;
+        self.memory = stats.total_memory;
+        self.disk = stats.total_disk;
+        self.cpu_usage = stats.cpu_usage as f64;
+        // Workers from before labels were reported send none
+        if !stats.labels.is_empty() {
+            self.labels = stats.labels.clone();
+        }
+    }
+}
+
+impl Manager {
+    pub fn get_nodes(&self) -> Vec<Node> {
+        self.nodes.clone()
+    }
+
+    async fn get_worker_stats(http: &reqwest::Client, address: &str) -> ManagerResult<SystemStats> {
+        let url = format!("http://{}/stats", address);
+
+        let resp = http
+            .get(&url)
+            .send()
+            .await
+            .map_err(|_| ManagerError::NetworkError(format!("Failed to connect to {}", url)))?;
+
+        if !resp.status().is_success() {
+            return Err(ManagerError::WorkerCommunication(format!(
+                "Worker {} returned status {}",
+                address,
+                resp.status().as_u16()
+            )));
+        }
+
+        resp.json().await.map_err(|_| {
+            ManagerError::WorkerCommunication(format!(
+                "Failed to parse stats from worker {}",
+                address
+            ))
+        })
+    }
+
+    // * Refreshes every healthy worker's node and labels from its /stats
+    // * endpoint. A worker that cannot be reached keeps its last known values.
+    // * The manager is not locked while the workers are asked.
+    pub async fn update_nodes(manager: &Mutex<Manager>) {
+        let (workers, http) = {
+            let manager = manager.lock().await;
+            (manager.healthy_workers(), manager.http.clone())
+        };
+        let stats = join_all(
+            workers
+                .iter()
+                .map(|worker| Manager::get_worker_stats(&http, &worker.address)),
+        )
+        .await;
+
+        let mut manager = manager.lock().await;
+        for (worker, stats) in workers.iter().zip(stats) {
+            match stats {
+                Ok(stats) => {
+                    if let Some(node) = manager.nodes.iter_mut().find(|n| n.name == worker.name) {
+                        node.apply_stats(&stats);
+                    }
+                    // Statically configured workers only learn their labels
+                    // this way
+                    if let Some(info) = manager.workers.get_mut(&worker.name)
+                        && !stats.labels.is_empty()
+                    {
+                        info.labels = stats.labels;
+                    }
+                }
+                Err(e) => eprintln!("Error fetching stats from {}: {:?}", worker.name, e),
+            }
+        }
+
+        manager.refresh_allocations();
+    }
+
+    // * Recomputes what every node has committed to from the tasks the manager
+    // * placed on it; tasks that finished no longer hold resources
+    pub fn refresh_allocations(&mut self) {
+        let mut allocations: HashMap<String, (u64, u64, u64)> = HashMap::new();
+        let mut task_labels: HashMap<String, Vec<HashMap<String, String>>> = HashMap::new();
+
+        let assignments = match self.task_worker_hash_map.entries() {
+            Ok(assignments) => assignments,
+            Err(e) => {
+                eprintln!("Error reading task assignments: {}", e);
+                return;
+            }
+        };
+
+        for (task_id, worker) in assignments {
+            let Ok(Some(task)) = self.task_db.get(&task_id) else {
+                continue;
+            };
+            if task.state.is_terminal() {
+                continue;
+            }
+
+            task_labels
+                .entry(worker.clone())
+                .or_default()
+                .push(task.labels.clone());
+            let entry = allocations.entry(worker).or_default();
+            entry.0 += task.memory;
+            entry.1 += task.disk;
+            entry.2 += 1;
+        }
+
+        for node in self.nodes.iter_mut() {
+            let (memory, disk, count) = allocations.get(&node.name).copied().unwrap_or_default();
+            node.memory_allocated = memory;
+            node.disk_allocated = disk;
+            node.task_count = count;
+            node.task_labels = task_labels.remove(&node.name).unwrap_or_default();
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use std::time::{Duration, SystemTime};
+
+use super::types::{
+    Manager, ManagerError, ManagerResult, WorkerInfo, WorkerRegistration, WorkerStatus,
+};
+use crate::lib::scheduler::types::Node;
+
+impl Manager {
+    pub fn register_worker(&mut self, registration: WorkerRegistration) -> WorkerInfo {
+        let info = WorkerInfo {
+            name: registration.name.clone(),
+            address: registration.address.clone(),
+            capacity: registration.capacity.clone(),
+            status: WorkerStatus::Healthy,
+            last_heartbeat: Some(SystemTime::now()),
+            failures: 0,
+            labels: registration.labels.clone(),
+        };
+
+        let mut node = Node::new(&registration.name, &registration.address, "worker");
+        node.cores = registration.capacity.cores;
+        node.memory = registration.capacity.memory;
+        node.disk = registration.capacity.disk;
+        node.labels = registration.labels.clone();
+
+        // Re-registering keeps the allocations already tracked for the node
+        match self.nodes.iter_mut().find(|n| n.name == node.name) {
+            Some(existing) => {
+                existing.ip = node.ip;
+                existing.cores = node.cores;
+                existing.memory = node.memory;
+                existing.disk = node.disk;
+                existing.labels = node.labels;
+            }
+            None => self.nodes.push(node),
+        }
+
+        println!(
+            "Registered worker {} at {}",
+            registration.name, registration.address
+        );
+        self.workers.insert(registration.name, info.clone());
+        info
+    }
+
+    pub fn heartbeat(&mut self, name: &str) -> ManagerResult<()> {
+        let worker = self
+            .workers
+            .get_mut(name)
+            .ok_or_else(|| ManagerError::WorkerNotFound(name.to_string()))?;
+
+        // A down worker has to answer a poll before it gets tasks again
+        if worker.status == WorkerStatus::Unhealthy {
+            println!("Worker {} is healthy again", name);
+            worker.status = WorkerStatus::Healthy;
+        }
+        worker.last_heartbeat = Some(SystemTime::now());
+        Ok(())
+    }
+
+    pub fn get_workers(&self) -> Vec<WorkerInfo> {
+        self.workers.values().cloned().collect()
+    }
+
+    pub fn healthy_workers(&self) -> Vec<WorkerInfo> {
+        self.workers
+            .values()
+            .filter(|worker| worker.status == WorkerStatus::Healthy)
+            .cloned()
+            .collect()
+    }
+
+    pub fn worker_address(&self, name: &str) -> ManagerResult<String> {
+        self.workers
+            .get(name)
+            .map(|worker| worker.address.clone())
+            .ok_or_else(|| ManagerError::WorkerNotFound(name.to_string()))
+    }
+
+    // * Counts a failed poll of the worker. Returns true when this failure
+    // * marks it down.
+    pub fn worker_unreachable(&mut self, name: &str, failure_threshold: u32) -> bool {
+        let Some(worker) = self.workers.get_mut(name) else {
+            return false;
+        };
+
+        worker.failures += 1;
+        if worker.status == WorkerStatus::Down || worker.failures < failure_threshold.max(1) {
+            return false;
+        }
+        println!(
+            "Worker {} failed {} polls in a row, marking it down",
+            name, worker.failures
+        );
+        worker.status = WorkerStatus::Down;
+        true
+    }
+
+    pub fn worker_reachable(&mut self, name: &str) {
+        let Some(worker) = self.workers.get_mut(name) else {
+            return;
+        };
+
+        worker.failures = 0;
+        if worker.status == WorkerStatus::Down {
+            println!("Worker {} is reachable again", name);
+            worker.status = WorkerStatus::Healthy;
+        }
+    }
+
+    // * Marks workers that missed their heartbeats as unhealthy and drops the
+    // * ones that stayed silent past the expiry
+    pub fn check_workers(&mut self, heartbeat_timeout: Duration, expiry: Duration) {
+        let now = SystemTime::now();
+        let mut expired = Vec::new();
+
+        for worker in self.workers.values_mut() {
+            let Some(last_heartbeat) = worker.last_heartbeat else {
+                continue;
+            };
+            let silence = now.duration_since(last_heartbeat).unwrap_or_default();
+
+            if silence >= expiry {
+                expired.push(worker.name.clone());
+            } else if silence >= heartbeat_timeout && worker.status == WorkerStatus::Healthy {
+                println!(
+                    "Worker {} missed its heartbeats for {:?}, marking it unhealthy",
+                    worker.name, silence
+                );
+                worker.status = WorkerStatus::Unhealthy;
+            }
+        }
+
+        for name in expired {
+            println!("Removing worker {} after missed heartbeats", name);
+            self.workers.remove(&name);
+            self.nodes.retain(|node| node.name != name);
+            if let Err(e) = self.reschedule_worker_tasks(&name) {
+                eprintln!("Error rescheduling tasks of worker {}: {:?}", name, e);
+            }
+        }
+    }
+}
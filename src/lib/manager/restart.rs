@@ -0,0 +1,230 @@
+use std::time::{Duration, SystemTime};
+
+use super::types::{Manager, ManagerResult};
+use crate::lib::tasks::types::{EventKind, HealthStatus, State, Task, TaskEvent};
+
+// * Delay before the next restart: `base` doubled for every earlier restart,
+// * capped at `max`
+pub fn restart_backoff(restart_count: u32, base: Duration, max: Duration) -> Duration {
+    base.checked_mul(2u32.saturating_pow(restart_count))
+        .unwrap_or(max)
+        .min(max)
+}
+
+// * The task without anything its last run on a worker reported
+fn fresh_run(task: Task) -> Task {
+    Task {
+        container_id: None,
+        assigned_ports: Vec::new(),
+        exit_code: None,
+        health: HealthStatus::Unknown,
+        health_failures: 0,
+        pull_events: Vec::new(),
+        start_time: None,
+        finish_time: None,
+        running_since: None,
+        ..task
+    }
+}
+
+impl Manager {
+    // * Forgets which worker a task was placed on, so its next start event is
+    // * scheduled from scratch
+    pub fn unassign(&mut self, task_id: &str) -> ManagerResult<()> {
+        if let Some(worker) = self.task_worker_hash_map.get(task_id)? {
+            let mut worker_tasks = self.worker_task_hash_map.get(&worker)?.unwrap_or_default();
+            worker_tasks.retain(|id| id != task_id);
+            self.worker_task_hash_map.put(&worker, worker_tasks)?;
+            self.task_worker_hash_map.delete(task_id)?;
+        }
+        Ok(())
+    }
+
+    // * Queues finished tasks whose restart policy asks for another run, once
+    // * their backoff has passed since they finished
+    pub fn restart_tasks(&mut self, backoff: Duration, max_backoff: Duration) -> ManagerResult<()> {
+        let now = SystemTime::now();
+
+        for task in self.task_db.list()? {
+            if !task.should_restart() {
+                continue;
+            }
+
+            let delay = restart_backoff(task.restart_count, backoff, max_backoff);
+            let due = task.finish_time.map_or(now, |finished| finished + delay);
+            if due > now {
+                continue;
+            }
+
+            println!(
+                "Restarting task {} after {:?} (restart {})",
+                task.id,
+                task.state,
+                task.restart_count + 1
+            );
+            self.unassign(&task.id)?;
+
+            let mut restarted = Task {
+                restart_count: task.restart_count + 1,
+                ..fresh_run(task.clone())
+            };
+            restarted.transition(
+                State::Restarting,
+                format!(
+                    "restart {} by {:?} policy",
+                    task.restart_count + 1,
+                    task.restart_policy
+                ),
+            );
+            restarted.next_generation();
+            self.task_db.put(&restarted.id, restarted.clone())?;
+            self.add_task(TaskEvent::new(EventKind::Restart, restarted))?;
+        }
+
+        self.refresh_allocations();
+        Ok(())
+    }
+
+    // * Places the live tasks of a worker that went away on other workers. A
+    // * task that was being stopped counts as stopped.
+    pub fn reschedule_worker_tasks(&mut self, worker: &str) -> ManagerResult<()> {
+        let task_ids = self.worker_task_hash_map.get(worker)?.unwrap_or_default();
+
+        for task_id in task_ids {
+            let Some(task) = self.task_db.get(&task_id)? else {
+                continue;
+            };
+            if task.state.is_terminal() {
+                continue;
+            }
+            self.unassign(&task_id)?;
+
+            let reason = format!("worker {} is down", worker);
+            let mut moved = fresh_run(task.clone());
+            moved.next_generation();
+            match task.state {
+                State::Stopping => {
+                    moved.transition(State::Completed, reason);
+                    moved.finish_time = Some(SystemTime::now());
+                    self.task_db.put(&task_id, moved)?;
+                    continue;
+                }
+                State::Running | State::Unknown => {
+                    moved.transition(State::Restarting, reason);
+                }
+                _ => {
+                    moved.transition(State::Scheduled, reason);
+                }
+            }
+
+            println!(
+                "Moving task {} off worker {} after {:?}",
+                task_id, worker, task.state
+            );
+            self.task_db.put(&task_id, moved.clone())?;
+            // A start still waiting for the task places it already
+            let queued = self.pending.iter().any(|event| {
+                event.task.id == task_id
+                    && matches!(event.kind, EventKind::Start | EventKind::Restart)
+            });
+            if !queued {
+                self.add_task(TaskEvent::new(EventKind::Restart, moved))?;
+            }
+        }
+
+        self.worker_task_hash_map.delete(worker)?;
+        self.refresh_allocations();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lib::tasks::types::RestartPolicy;
+
+    #[test]
+    fn backoff_doubles_per_restart_up_to_the_cap() {
+        let base = Duration::from_secs(1);
+        let max = Duration::from_secs(60);
+
+        for (restart_count, expected) in [
+            (0, 1),
+            (1, 2),
+            (2, 4),
+            (5, 32),
+            (6, 60),
+            (7, 60),
+            (31, 60),
+            (32, 60),
+            (u32::MAX, 60),
+        ] {
+            assert_eq!(
+                restart_backoff(restart_count, base, max),
+                Duration::from_secs(expected),
+                "restart {}",
+                restart_count
+            );
+        }
+        assert_eq!(restart_backoff(3, Duration::ZERO, max), Duration::ZERO);
+    }
+
+    #[test]
+    fn should_restart_follows_the_policy_and_max_restarts() {
+        let task = |policy, state, exit_code, restart_count, max_restarts| Task {
+            restart_policy: policy,
+            state,
+            exit_code,
+            restart_count,
+            max_restarts,
+            ..Task::default()
+        };
+
+        for (task, expected) in [
+            (
+                task(RestartPolicy::Never, State::Failed, Some(1), 0, 0),
+                false,
+            ),
+            (
+                task(RestartPolicy::OnFailure, State::Failed, Some(1), 0, 0),
+                true,
+            ),
+            (
+                task(RestartPolicy::OnFailure, State::Completed, Some(0), 0, 0),
+                false,
+            ),
+            (
+                task(RestartPolicy::Always, State::Completed, Some(0), 0, 0),
+                true,
+            ),
+            // Stopped through the API, so no exit code was recorded
+            (
+                task(RestartPolicy::Always, State::Completed, None, 0, 0),
+                false,
+            ),
+            (
+                task(RestartPolicy::Always, State::Running, None, 0, 0),
+                false,
+            ),
+            // A max of 0 means no limit
+            (
+                task(RestartPolicy::OnFailure, State::Failed, Some(1), 1000, 0),
+                true,
+            ),
+            (
+                task(RestartPolicy::OnFailure, State::Failed, Some(1), 2, 3),
+                true,
+            ),
+            (
+                task(RestartPolicy::OnFailure, State::Failed, Some(1), 3, 3),
+                false,
+            ),
+            (
+                task(RestartPolicy::Always, State::Completed, Some(0), 4, 3),
+                false,
+            ),
+        ] {
+            assert_eq!(task.should_restart(), expected, "{:?}", task);
+        }
+    }
+}
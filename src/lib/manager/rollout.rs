@@ -0,0 +1,417 @@
+use std::time::{Duration, SystemTime};
+
+use super::{
+    services::counts_as_replica,
+    types::{
+        Manager, ManagerResult, Rollout, RolloutState, Service, ServiceRevision, UpdateConfig,
+    },
+};
+use crate::lib::tasks::types::{EventKind, HealthStatus, State, Task, TaskEvent};
+
+// * Revisions a service keeps to roll back to
+pub const MAX_SERVICE_REVISIONS: usize = 10;
+
+impl UpdateConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_unavailable == 0 && self.max_surge == 0 {
+            return Err(
+                "An update needs max_unavailable or max_surge above 0 to make progress".to_string(),
+            );
+        }
+        Ok(())
+    }
+}
+
+// * Whether the task was created from `template`. Replicas are named after
+// * their service, so the template's name does not matter.
+fn runs_template(task: &Task, template: &Task) -> bool {
+    task.same_spec(&Task {
+        name: task.name.clone(),
+        ..template.clone()
+    })
+}
+
+// * Whether the task serves: it runs, passes its health check if it has
+// * one, and the manager has seen it running for at least `wait`
+fn available(task: &Task, wait: Duration, now: SystemTime) -> bool {
+    task.state == State::Running
+        && match task.health_check {
+            Some(_) => task.health == HealthStatus::Healthy,
+            None => task.health != HealthStatus::Unhealthy,
+        }
+        && task.running_since.is_some_and(|since| since + wait <= now)
+}
+
+// * Why a task of the new template counts as failed, if it does
+fn rollout_failure(task: &Task) -> Option<String> {
+    if task.state == State::Failed {
+        return Some(match task.exit_code {
+            Some(code) => format!("failed with exit code {}", code),
+            None => "failed".to_string(),
+        });
+    }
+    if task.restart_count > 0 {
+        return Some(format!("was restarted {} times", task.restart_count));
+    }
+    if task.health == HealthStatus::Unhealthy {
+        return Some("is unhealthy".to_string());
+    }
+    None
+}
+
+impl Manager {
+    // * Makes `template` the next revision of the service and starts rolling
+    // * it out. A template equal to the current one changes nothing.
+    pub fn update_service(
+        &mut self,
+        service: Service,
+        template: Task,
+        update_config: Option<UpdateConfig>,
+        reason: String,
+    ) -> ManagerResult<Service> {
+        let mut service = service;
+        if let Some(update_config) = update_config {
+            service.update_config = update_config;
+        }
+        if service.template.same_spec(&template) {
+            self.service_db.put(&service.id, service.clone())?;
+            return Ok(service);
+        }
+
+        let now = SystemTime::now();
+        let revision = service.revision + 1;
+        println!(
+            "Rolling out revision {} of service {}: {}",
+            revision, service.name, reason
+        );
+        service.revisions.push(ServiceRevision {
+            revision,
+            template: template.clone(),
+            created: now,
+        });
+        let excess = service
+            .revisions
+            .len()
+            .saturating_sub(MAX_SERVICE_REVISIONS);
+        service.revisions.drain(..excess);
+
+        service.rollout = Some(Rollout {
+            from_revision: service.revision,
+            to_revision: revision,
+            state: RolloutState::Progressing,
+            message: reason,
+            started: now,
+            finished: None,
+        });
+        service.revision = revision;
+        service.template = template;
+        self.service_db.put(&service.id, service.clone())?;
+        self.reconcile_service(&service)?;
+        Ok(service)
+    }
+
+    // * Rolls the template of the revision before the current one out again,
+    // * as a new revision. Returns None when there is no earlier revision or
+    // * it has the current template.
+    pub fn rollback_service(&mut self, service: Service) -> ManagerResult<Option<Service>> {
+        let Some(previous) = service
+            .revisions
+            .iter()
+            .rev()
+            .nth(1)
+            .filter(|previous| !previous.template.same_spec(&service.template))
+            .cloned()
+        else {
+            return Ok(None);
+        };
+        let reason = format!("rollback to revision {}", previous.revision);
+        self.update_service(service, previous.template, None, reason)
+            .map(Some)
+    }
+
+    // * Continues a paused rollout. Returns None when no rollout is paused.
+    pub fn resume_service(&mut self, service: Service) -> ManagerResult<Option<Service>> {
+        let mut service = service;
+        let Some(rollout) = service
+            .rollout
+            .as_mut()
+            .filter(|rollout| rollout.state == RolloutState::Paused)
+        else {
+            return Ok(None);
+        };
+
+        println!(
+            "Resuming rollout of revision {} of service {}",
+            rollout.to_revision, service.name
+        );
+        rollout.state = RolloutState::Progressing;
+        rollout.message = "resumed".to_string();
+        // Failures from before the pause do not pause it again
+        rollout.started = SystemTime::now();
+        self.service_db.put(&service.id, service.clone())?;
+        self.reconcile_service(&service)?;
+        Ok(Some(service))
+    }
+
+    // * One step of a rollout: retires old tasks as long as enough replicas
+    // * stay available and starts new ones within the surge. New tasks only
+    // * count as available once they waited out `health_wait`, which makes
+    // * each batch wait for the one before it.
+    pub fn roll_out(&mut self, service: Service) -> ManagerResult<()> {
+        let mut service = service;
+        let Some(mut rollout) = service.rollout.take() else {
+            return Ok(());
+        };
+        let now = SystemTime::now();
+        let tasks = self.service_tasks(&service.id)?;
+        let template = service.replica_template();
+
+        // A task of the new template failing since the rollout started
+        // pauses it
+        let failure = tasks
+            .iter()
+            .filter(|task| runs_template(task, &template))
+            .filter(|task| {
+                task.history
+                    .first()
+                    .is_some_and(|first| first.timestamp >= rollout.started)
+            })
+            .find_map(|task| rollout_failure(task).map(|reason| (task.id.clone(), reason)));
+        if let Some((task_id, reason)) = failure {
+            println!(
+                "Pausing rollout of revision {} of service {}: task {} {}",
+                rollout.to_revision, service.name, task_id, reason
+            );
+            rollout.state = RolloutState::Paused;
+            rollout.message = format!("task {} {}", task_id, reason);
+            service.rollout = Some(rollout);
+            self.service_db.put(&service.id.clone(), service)?;
+            return Ok(());
+        }
+
+        let (new, mut old): (Vec<Task>, Vec<Task>) = tasks
+            .into_iter()
+            .filter(counts_as_replica)
+            .partition(|task| runs_template(task, &template));
+        let desired = service.replicas as usize;
+        let wait = Duration::from_secs(service.update_config.health_wait);
+        let new_available = new.iter().filter(|task| available(task, wait, now)).count();
+
+        if old.is_empty() && new_available >= desired {
+            println!(
+                "Rollout of revision {} of service {} completed",
+                rollout.to_revision, service.name
+            );
+            rollout.state = RolloutState::Completed;
+            rollout.finished = Some(now);
+            service.rollout = Some(rollout);
+            self.service_db.put(&service.id, service.clone())?;
+            // Replicas above the count are trimmed like for any service
+            return self.reconcile_service(&service);
+        }
+
+        let min_available = desired.saturating_sub(service.update_config.max_unavailable as usize);
+        let mut serving = new_available
+            + old
+                .iter()
+                .filter(|task| available(task, Duration::ZERO, now))
+                .count();
+        let mut total = new.len() + old.len();
+
+        // Old tasks that do not serve anyway go first
+        old.sort_by_key(|task| available(task, Duration::ZERO, now));
+        for task in old {
+            let was_serving = available(&task, Duration::ZERO, now);
+            if was_serving {
+                if serving <= min_available {
+                    break;
+                }
+                serving -= 1;
+            }
+            total -= 1;
+            println!(
+                "Replacing task {} of service {} with revision {}",
+                task.id, service.name, rollout.to_revision
+            );
+            self.retire(
+                task,
+                format!("replaced by revision {}", rollout.to_revision),
+            )?;
+        }
+
+        let room = (desired + service.update_config.max_surge as usize).saturating_sub(total);
+        let missing = desired.saturating_sub(new.len());
+        for _ in 0..room.min(missing) {
+            let task = service.new_replica();
+            println!(
+                "Starting task {} for revision {} of service {}",
+                task.id, rollout.to_revision, service.name
+            );
+            self.add_task(TaskEvent::new(EventKind::Start, task))?;
+        }
+
+        service.rollout = Some(rollout);
+        self.service_db.put(&service.id.clone(), service)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lib::{
+        manager::types::ServiceSpec, scheduler::types::SchedulerType, tasks::types::RestartPolicy,
+    };
+
+    fn template(image: &str) -> Task {
+        Task {
+            image: image.to_string(),
+            restart_policy: RestartPolicy::Never,
+            ..Task::default()
+        }
+    }
+
+    // * Plays the worker: queued starts run on "w1" and have been running for
+    // * a minute, queued stops complete. Returns how many of each it handled.
+    fn settle(manager: &mut Manager) -> (usize, usize) {
+        let (mut started, mut stopped) = (0, 0);
+        for event in std::mem::take(&mut manager.pending) {
+            let mut task = event.task;
+            match event.kind {
+                EventKind::Start => {
+                    task.transition(State::Scheduled, "placed on worker w1");
+                    task.transition(State::Running, "container started");
+                    task.running_since = Some(SystemTime::now() - Duration::from_secs(60));
+                    manager
+                        .task_worker_hash_map
+                        .put(&task.id, "w1".to_string())
+                        .unwrap();
+                    started += 1;
+                }
+                EventKind::Stop => {
+                    task.transition(State::Completed, "stopped on request");
+                    stopped += 1;
+                }
+                _ => continue,
+            }
+            manager.task_db.put(&task.id.clone(), task).unwrap();
+        }
+        manager.save_pending().unwrap();
+        (started, stopped)
+    }
+
+    // * Runs one rollout step on the stored service and settles what it queued
+    fn step(manager: &mut Manager, service_id: &str) -> (usize, usize) {
+        let service = manager.find_service(service_id).unwrap().unwrap();
+        manager.reconcile_service(&service).unwrap();
+        settle(manager)
+    }
+
+    fn service(manager: &mut Manager, max_unavailable: u32, max_surge: u32) -> Service {
+        let service = manager
+            .create_service(ServiceSpec {
+                name: "web".to_string(),
+                replicas: 4,
+                template: template("web:1"),
+                update_config: UpdateConfig {
+                    max_unavailable,
+                    max_surge,
+                    health_wait: 0,
+                },
+            })
+            .unwrap();
+        assert_eq!(settle(manager), (4, 0));
+        service
+    }
+
+    fn tasks_running(manager: &Manager, service_id: &str, image: &str) -> Vec<Task> {
+        manager
+            .service_tasks(service_id)
+            .unwrap()
+            .into_iter()
+            .filter(|task| task.state == State::Running && task.image == image)
+            .collect()
+    }
+
+    #[test]
+    fn a_rollout_replaces_replicas_in_batches() {
+        let mut manager = Manager::new(Vec::new(), SchedulerType::RoundRobin);
+        let service = service(&mut manager, 1, 1);
+
+        let service = manager
+            .update_service(service, template("web:2"), None, "new image".to_string())
+            .unwrap();
+        // One replica may be down and one may run on top
+        assert_eq!(settle(&mut manager), (2, 1));
+        assert_eq!(step(&mut manager, &service.id), (2, 2));
+        assert_eq!(step(&mut manager, &service.id), (0, 1));
+        assert_eq!(step(&mut manager, &service.id), (0, 0));
+
+        let service = manager.find_service(&service.id).unwrap().unwrap();
+        assert_eq!(service.rollout.unwrap().state, RolloutState::Completed);
+        assert_eq!(tasks_running(&manager, &service.id, "web:2").len(), 4);
+        assert!(tasks_running(&manager, &service.id, "web:1").is_empty());
+    }
+
+    #[test]
+    fn an_unhealthy_batch_pauses_the_rollout() {
+        let mut manager = Manager::new(Vec::new(), SchedulerType::RoundRobin);
+        let service = service(&mut manager, 1, 0);
+
+        let service = manager
+            .update_service(service, template("web:2"), None, "new image".to_string())
+            .unwrap();
+        assert_eq!(settle(&mut manager), (1, 1));
+
+        let mut unhealthy = tasks_running(&manager, &service.id, "web:2").remove(0);
+        unhealthy.health = HealthStatus::Unhealthy;
+        manager
+            .task_db
+            .put(&unhealthy.id, unhealthy.clone())
+            .unwrap();
+
+        // Nothing more is replaced once it pauses
+        assert_eq!(step(&mut manager, &service.id), (0, 0));
+        let rollout = manager
+            .find_service(&service.id)
+            .unwrap()
+            .unwrap()
+            .rollout
+            .unwrap();
+        assert_eq!(rollout.state, RolloutState::Paused);
+        assert_eq!(
+            rollout.message,
+            format!("task {} is unhealthy", unhealthy.id)
+        );
+        assert_eq!(step(&mut manager, &service.id), (0, 0));
+        assert_eq!(tasks_running(&manager, &service.id, "web:1").len(), 3);
+    }
+
+    #[test]
+    fn a_paused_rollout_replaces_replicas_from_the_previous_revision() {
+        let mut manager = Manager::new(Vec::new(), SchedulerType::RoundRobin);
+        let service = service(&mut manager, 1, 0);
+
+        let service = manager
+            .update_service(service, template("web:2"), None, "new image".to_string())
+            .unwrap();
+        settle(&mut manager);
+        let mut failed = tasks_running(&manager, &service.id, "web:2").remove(0);
+        failed.exited(Some(1), "container exited");
+        manager.task_db.put(&failed.id.clone(), failed).unwrap();
+
+        // The first step pauses, the next one fills the gap it left
+        assert_eq!(step(&mut manager, &service.id), (0, 0));
+        assert_eq!(step(&mut manager, &service.id), (1, 0));
+        assert_eq!(tasks_running(&manager, &service.id, "web:1").len(), 4);
+        assert!(tasks_running(&manager, &service.id, "web:2").is_empty());
+    }
+
+    #[test]
+    fn rolling_back_a_service_with_one_revision_changes_nothing() {
+        let mut manager = Manager::new(Vec::new(), SchedulerType::RoundRobin);
+        let service = service(&mut manager, 1, 0);
+
+        assert!(manager.rollback_service(service).unwrap().is_none());
+    }
+}
@@ -0,0 +1,349 @@
+use std::{cmp::Reverse, collections::HashMap, time::SystemTime};
+
+use super::types::{
+    Manager, ManagerResult, RolloutState, Service, ServiceRevision, ServiceSpec, ServiceStatus,
+};
+use crate::lib::tasks::{
+    manifest::valid_name,
+    types::{EventKind, RestartPolicy, State, Task, TaskEvent},
+};
+
+impl ServiceSpec {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.len() > 63 || !valid_name(&self.name) {
+            return Err(format!(
+                "Service name {:?} must be 1 to 63 letters, digits, '_', '.' or '-' and start with a letter or digit",
+                self.name
+            ));
+        }
+        self.update_config.validate()?;
+        validate_template(&self.name, &self.template)
+    }
+}
+
+pub fn validate_template(service: &str, template: &Task) -> Result<(), String> {
+    if template.image.is_empty() && template.command.is_empty() {
+        return Err(format!(
+            "Service {} needs an image or a command to run",
+            service
+        ));
+    }
+    Ok(())
+}
+
+// * Label every replica carries, set to the name of its service
+pub const SERVICE_LABEL: &str = "r_cube.service";
+
+impl Service {
+    // * The template with the service label added. Unless the template has
+    // * affinity rules of its own, replicas also avoid each other, which
+    // * spreads them across workers.
+    pub fn replica_template(&self) -> Task {
+        self.labelled(self.template.clone())
+    }
+
+    fn labelled(&self, mut template: Task) -> Task {
+        template
+            .labels
+            .insert(SERVICE_LABEL.to_string(), self.name.clone());
+        let placement = &mut template.placement;
+        if placement.affinity.is_empty() && placement.anti_affinity.is_empty() {
+            placement
+                .anti_affinity
+                .push(format!("{}={}", SERVICE_LABEL, self.name));
+        }
+        template
+    }
+
+    // * The template replicas are started from. While a rollout is paused
+    // * that is the template it rolls out from, so replacements do not run
+    // * the template that made it pause.
+    pub fn serving_template(&self) -> Task {
+        let previous = self
+            .rollout
+            .as_ref()
+            .filter(|rollout| rollout.state == RolloutState::Paused)
+            .and_then(|rollout| {
+                self.revisions
+                    .iter()
+                    .find(|revision| revision.revision == rollout.from_revision)
+            });
+        match previous {
+            Some(previous) => self.labelled(previous.template.clone()),
+            None => self.replica_template(),
+        }
+    }
+
+    // * A new task from the service's serving template, named after the
+    // * service
+    pub fn new_replica(&self) -> Task {
+        let mut task = Task::default().with_spec(&self.serving_template());
+        task.name = format!("{}-{}", self.name, &task.id[..8]);
+        task.service_id = Some(self.id.clone());
+        task
+    }
+}
+
+// * Whether the task counts towards its service's replicas: it runs or is
+// * about to, or it finished and its restart policy brings it back
+pub fn counts_as_replica(task: &Task) -> bool {
+    (!task.state.is_terminal() && task.state != State::Stopping) || task.should_restart()
+}
+
+impl Manager {
+    // * Looks a service up by id, then by name
+    pub fn find_service(&self, key: &str) -> ManagerResult<Option<Service>> {
+        if let Some(service) = self.service_db.get(key)? {
+            return Ok(Some(service));
+        }
+        Ok(self
+            .service_db
+            .list()?
+            .into_iter()
+            .find(|service| service.name == key))
+    }
+
+    pub fn create_service(&mut self, spec: ServiceSpec) -> ManagerResult<Service> {
+        let created = SystemTime::now();
+        let service = Service {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: spec.name,
+            replicas: spec.replicas,
+            revision: 1,
+            revisions: vec![ServiceRevision {
+                revision: 1,
+                template: spec.template.clone(),
+                created,
+            }],
+            template: spec.template,
+            created,
+            update_config: spec.update_config,
+            rollout: None,
+        };
+        self.service_db.put(&service.id, service.clone())?;
+        println!(
+            "Created service {} with {} replicas",
+            service.name, service.replicas
+        );
+        self.reconcile_service(&service)?;
+        Ok(service)
+    }
+
+    pub fn scale_service(&mut self, service: Service, replicas: u32) -> ManagerResult<Service> {
+        println!(
+            "Scaling service {} from {} to {} replicas",
+            service.name, service.replicas, replicas
+        );
+        let service = Service {
+            replicas,
+            ..service
+        };
+        self.service_db.put(&service.id, service.clone())?;
+        self.reconcile_service(&service)?;
+        Ok(service)
+    }
+
+    // * Stops every task of the service and forgets it
+    pub fn delete_service(&mut self, service: Service) -> ManagerResult<()> {
+        println!("Deleting service {}", service.name);
+        self.reconcile_service(&Service {
+            replicas: 0,
+            rollout: None,
+            ..service.clone()
+        })?;
+        self.service_db.delete(&service.id)?;
+        Ok(())
+    }
+
+    // * Every task of the service, finished ones included. Tasks whose start
+    // * is still queued are not in the task store yet.
+    pub fn service_tasks(&self, service_id: &str) -> ManagerResult<Vec<Task>> {
+        let mut tasks: Vec<Task> = self
+            .task_db
+            .list()?
+            .into_iter()
+            .filter(|task| task.service_id.as_deref() == Some(service_id))
+            .collect();
+
+        for event in &self.pending {
+            if event.kind == EventKind::Start
+                && event.task.service_id.as_deref() == Some(service_id)
+                && !tasks.iter().any(|task| task.id == event.task.id)
+            {
+                tasks.push(event.task.clone());
+            }
+        }
+        Ok(tasks)
+    }
+
+    pub fn service_status(&self, service: Service) -> ManagerResult<ServiceStatus> {
+        let tasks = self.service_tasks(&service.id)?;
+
+        let mut states = HashMap::new();
+        for task in &tasks {
+            *states.entry(task.state.clone()).or_insert(0) += 1;
+        }
+
+        Ok(ServiceStatus {
+            running: states.get(&State::Running).copied().unwrap_or(0),
+            states,
+            tasks: tasks
+                .into_iter()
+                .filter(counts_as_replica)
+                .map(|task| task.id)
+                .collect(),
+            service,
+        })
+    }
+
+    // * Starts or retires tasks until every service has its replica count
+    pub fn reconcile_services(&mut self) -> ManagerResult<()> {
+        for service in self.service_db.list()? {
+            self.reconcile_service(&service)?;
+        }
+        Ok(())
+    }
+
+    pub fn reconcile_service(&mut self, service: &Service) -> ManagerResult<()> {
+        if service
+            .rollout
+            .as_ref()
+            .is_some_and(|rollout| rollout.state == RolloutState::Progressing)
+        {
+            return self.roll_out(service.clone());
+        }
+
+        let mut replicas: Vec<Task> = self
+            .service_tasks(&service.id)?
+            .into_iter()
+            .filter(counts_as_replica)
+            .collect();
+        let live = replicas.len() as u32;
+
+        if live < service.replicas {
+            for _ in live..service.replicas {
+                let task = service.new_replica();
+                println!("Starting task {} for service {}", task.id, service.name);
+                self.add_task(TaskEvent::new(EventKind::Start, task))?;
+            }
+            return Ok(());
+        }
+
+        // Tasks that are not placed or not running yet go first, then the
+        // most recently started ones
+        let mut placed = HashMap::new();
+        for task in &replicas {
+            placed.insert(
+                task.id.clone(),
+                self.task_worker_hash_map.get(&task.id)?.is_some(),
+            );
+        }
+        replicas.sort_by_key(|task| {
+            (
+                placed[&task.id],
+                task.state == State::Running,
+                Reverse(task.start_time),
+            )
+        });
+        for task in replicas
+            .into_iter()
+            .take((live - service.replicas) as usize)
+        {
+            println!("Retiring task {} of service {}", task.id, service.name);
+            self.retire(task, format!("service {} scaled down", service.name))?;
+        }
+        Ok(())
+    }
+
+    // * Takes a task out of its service: a placed task is stopped, one that
+    // * is not placed is cancelled and a finished one is no longer restarted
+    pub fn retire(&mut self, mut task: Task, reason: String) -> ManagerResult<()> {
+        let task_id = task.id.clone();
+        self.pending.retain(|event| event.task.id != task_id);
+        self.save_pending()?;
+        if self.task_db.get(&task_id)?.is_none() {
+            return Ok(());
+        }
+
+        if task.state.is_terminal() {
+            task.restart_policy = RestartPolicy::Never;
+            self.task_db.put(&task_id, task)?;
+        } else if self.task_worker_hash_map.get(&task_id)?.is_some() {
+            task.transition(State::Stopping, reason);
+            task.next_generation();
+            self.task_db.put(&task_id, task.clone())?;
+            self.add_task(TaskEvent::new(EventKind::Stop, task))?;
+        } else {
+            task.transition(State::Cancelled, reason);
+            task.next_generation();
+            task.finish_time = Some(SystemTime::now());
+            self.task_db.put(&task_id, task)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lib::{manager::types::UpdateConfig, scheduler::types::SchedulerType};
+
+    fn queued(manager: &Manager, kind: EventKind) -> usize {
+        manager
+            .pending
+            .iter()
+            .filter(|event| event.kind == kind)
+            .count()
+    }
+
+    fn create(manager: &mut Manager, replicas: u32) -> Service {
+        manager
+            .create_service(ServiceSpec {
+                name: "web".to_string(),
+                replicas,
+                template: Task {
+                    image: "web:1".to_string(),
+                    ..Task::default()
+                },
+                update_config: UpdateConfig::default(),
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn scaling_up_queues_the_missing_replicas() {
+        let mut manager = Manager::new(Vec::new(), SchedulerType::RoundRobin);
+        let service = create(&mut manager, 2);
+        assert_eq!(queued(&manager, EventKind::Start), 2);
+
+        manager.scale_service(service, 5).unwrap();
+        assert_eq!(queued(&manager, EventKind::Start), 5);
+        assert_eq!(queued(&manager, EventKind::Stop), 0);
+    }
+
+    #[test]
+    fn scaling_down_drops_queued_replicas_before_stopping_running_ones() {
+        let mut manager = Manager::new(Vec::new(), SchedulerType::RoundRobin);
+        let service = create(&mut manager, 3);
+
+        // Two replicas run on a worker, the third is still queued
+        for event in manager.pending.drain(..2).collect::<Vec<_>>() {
+            let mut task = event.task;
+            task.transition(State::Scheduled, "placed on worker w1");
+            task.transition(State::Running, "container started");
+            manager
+                .task_worker_hash_map
+                .put(&task.id, "w1".to_string())
+                .unwrap();
+            manager.task_db.put(&task.id.clone(), task).unwrap();
+        }
+        manager.save_pending().unwrap();
+
+        let service = manager.scale_service(service, 1).unwrap();
+        assert_eq!(queued(&manager, EventKind::Start), 0);
+        assert_eq!(queued(&manager, EventKind::Stop), 1);
+        let status = manager.service_status(service).unwrap();
+        assert_eq!(status.tasks.len(), 1);
+        assert_eq!(status.states.get(&State::Stopping), Some(&1));
+    }
+}
@@ -1,22 +1,309 @@
 use tokio::sync::Mutex;
 
-use crate::lib::tasks::types::Task;
+use crate::lib::scheduler::scheduler::Scheduler;
+use crate::lib::scheduler::types::Node;
+use crate::lib::store::{
+    store::Store,
+    types::{FileStore, InMemoryStore, StoreError, StoreResult},
+};
 use crate::lib::tasks::types::TaskEvent;
+use crate::lib::tasks::types::{State, Task};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 use std::error::Error;
 use std::fmt;
+use std::path::Path;
 use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
 #[derive(Debug, Clone)]
 pub struct Manager {
     pub pending: std::collections::VecDeque<TaskEvent>,
-    pub task_db: HashMap<String, Task>,
-    pub event_db: HashMap<String, TaskEvent>,
-    pub workers: Vec<String>,
-    pub worker_task_hash_map: HashMap<String, Vec<String>>,
-    pub task_worker_hash_map: HashMap<String, String>,
-    pub last_worker: u16,
+    // * Holds the pending queue, so queued events survive a restart
+    pub pending_db: Arc<dyn Store<std::collections::VecDeque<TaskEvent>>>,
+    pub task_db: Arc<dyn Store<Task>>,
+    pub event_db: Arc<dyn Store<TaskEvent>>,
+    // * Workers keyed by name, either registered over the API or configured
+    // * statically through `Manager::new`
+    pub workers: HashMap<String, WorkerInfo>,
+    pub worker_task_hash_map: Arc<dyn Store<Vec<String>>>,
+    pub task_worker_hash_map: Arc<dyn Store<String>>,
+    // * Services keyed by id, reconciled towards their replica count
+    pub service_db: Arc<dyn Store<Service>>,
+    pub nodes: Vec<Node>,
+    pub scheduler: Arc<dyn Scheduler>,
+    // * Client for every request to workers, with timeouts so a hung worker
+    // * does not stall the reconciliation loop
+    pub http: reqwest::Client,
+}
+
+// * Dispatch is a pending event along with the worker it goes to. It is
+// * worked out under the manager lock and sent after it is released.
+#[derive(Debug, Clone)]
+pub struct Dispatch {
+    pub worker: String,
+    pub address: String,
+    pub event: TaskEvent,
+}
+
+// * ManagerStores holds the stores backing the manager's task bookkeeping
+pub struct ManagerStores {
+    pub tasks: Arc<dyn Store<Task>>,
+    pub events: Arc<dyn Store<TaskEvent>>,
+    pub pending: Arc<dyn Store<std::collections::VecDeque<TaskEvent>>>,
+    pub worker_tasks: Arc<dyn Store<Vec<String>>>,
+    pub task_workers: Arc<dyn Store<String>>,
+    pub services: Arc<dyn Store<Service>>,
+}
+
+impl Default for ManagerStores {
+    fn default() -> Self {
+        ManagerStores {
+            tasks: Arc::new(InMemoryStore::new()),
+            events: Arc::new(InMemoryStore::new()),
+            pending: Arc::new(InMemoryStore::new()),
+            worker_tasks: Arc::new(InMemoryStore::new()),
+            task_workers: Arc::new(InMemoryStore::new()),
+            services: Arc::new(InMemoryStore::new()),
+        }
+    }
+}
+
+impl ManagerStores {
+    // * Opens file backed stores under `dir`, reloading whatever a previous
+    // * manager process left there
+    pub fn open(dir: &Path) -> StoreResult<Self> {
+        Ok(ManagerStores {
+            tasks: Arc::new(FileStore::open(dir.join("tasks.log"))?),
+            events: Arc::new(FileStore::open(dir.join("events.log"))?),
+            pending: Arc::new(FileStore::open(dir.join("pending.log"))?),
+            worker_tasks: Arc::new(FileStore::open(dir.join("worker_tasks.log"))?),
+            task_workers: Arc::new(FileStore::open(dir.join("task_workers.log"))?),
+            services: Arc::new(FileStore::open(dir.join("services.log"))?),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WorkerStatus {
+    Healthy,
+    // * Missed its heartbeats
+    Unhealthy,
+    // * Failed too many polls in a row. Its tasks were placed elsewhere, and
+    // * it only comes back once a poll succeeds again.
+    Down,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WorkerCapacity {
+    pub cores: u64,
+    // * Total memory in bytes
+    pub memory: u64,
+    // * Total disk space in bytes
+    pub disk: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkerInfo {
+    pub name: String,
+    // * host:port of the worker API
+    pub address: String,
+    pub capacity: WorkerCapacity,
+    pub status: WorkerStatus,
+    // * None for statically configured workers, which never expire
+    pub last_heartbeat: Option<SystemTime>,
+    // * Polls of the worker's tasks that failed in a row
+    #[serde(default)]
+    pub failures: u32,
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+}
+
+// * WorkerRegistration is the body a worker sends to POST /workers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkerRegistration {
+    pub name: String,
+    pub address: String,
+    pub capacity: WorkerCapacity,
+    // * Labels task placement rules match, like "zone=a"
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+}
+
+// * ManagerIntervals controls how often the reconciliation loop started by
+// * `Manager::run` dispatches pending events, polls the workers' tasks and
+// * stats, checks their heartbeats and restarts finished tasks
+#[derive(Debug, Clone)]
+pub struct ManagerIntervals {
+    pub send_work: Duration,
+    pub update_tasks: Duration,
+    pub check_workers: Duration,
+    pub update_nodes: Duration,
+    pub restart_tasks: Duration,
+    pub reconcile_services: Duration,
+    // * Wait before the first restart of a task, doubled for each later one
+    pub restart_backoff: Duration,
+    // * Longest wait between two restarts of a task
+    pub max_restart_backoff: Duration,
+    // * A worker without a heartbeat for this long is marked unhealthy
+    pub heartbeat_timeout: Duration,
+    // * A worker without a heartbeat for this long is removed
+    pub worker_expiry: Duration,
+    // * Failed polls in a row after which a worker is down and its tasks are
+    // * placed on other workers
+    pub worker_failure_threshold: u32,
+}
+
+impl Default for ManagerIntervals {
+    fn default() -> Self {
+        ManagerIntervals {
+            send_work: Duration::from_secs(10),
+            update_tasks: Duration::from_secs(15),
+            check_workers: Duration::from_secs(10),
+            update_nodes: Duration::from_secs(15),
+            restart_tasks: Duration::from_secs(5),
+            reconcile_services: Duration::from_secs(10),
+            restart_backoff: Duration::from_secs(5),
+            max_restart_backoff: Duration::from_secs(300),
+            heartbeat_timeout: Duration::from_secs(30),
+            worker_expiry: Duration::from_secs(120),
+            worker_failure_threshold: 3,
+        }
+    }
+}
+
+// * Service keeps `replicas` tasks built from its template running. The
+// * manager starts tasks until that many are live and replaces the ones that
+// * finish for good.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Service {
+    pub id: String,
+    pub name: String,
+    pub replicas: u32,
+    // * Spec every task of the service is created from
+    pub template: Task,
+    pub created: SystemTime,
+    // * Revision of the current template, starting at 1
+    #[serde(default)]
+    pub revision: u32,
+    // * Recent templates of the service, oldest first, the current one last
+    #[serde(default)]
+    pub revisions: Vec<ServiceRevision>,
+    #[serde(default)]
+    pub update_config: UpdateConfig,
+    // * The last rollout of a new template, None before the first update
+    #[serde(default)]
+    pub rollout: Option<Rollout>,
+}
+
+// * ServiceRevision is a template the service ran at some point
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceRevision {
+    pub revision: u32,
+    pub template: Task,
+    pub created: SystemTime,
+}
+
+// * UpdateConfig controls how a new template replaces the tasks of a
+// * service, one batch at a time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UpdateConfig {
+    // * Replicas that may be unavailable while the rollout runs
+    pub max_unavailable: u32,
+    // * Tasks that may run on top of the replicas while the rollout runs
+    pub max_surge: u32,
+    // * Seconds a new task has to run, and pass its health check if it has
+    // * one, before it counts as available
+    pub health_wait: u64,
+}
+
+impl Default for UpdateConfig {
+    fn default() -> Self {
+        UpdateConfig {
+            max_unavailable: 1,
+            max_surge: 0,
+            health_wait: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RolloutState {
+    // * Replacing old tasks
+    Progressing,
+    // * A new task failed; nothing is replaced until the rollout is resumed
+    // * or rolled back
+    Paused,
+    Completed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rollout {
+    pub from_revision: u32,
+    pub to_revision: u32,
+    pub state: RolloutState,
+    // * Why the rollout started, or why it paused
+    pub message: String,
+    pub started: SystemTime,
+    pub finished: Option<SystemTime>,
+}
+
+// * ServiceSpec is the body of POST /services
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceSpec {
+    pub name: String,
+    pub replicas: u32,
+    pub template: Task,
+    #[serde(default)]
+    pub update_config: UpdateConfig,
+}
+
+// * ServiceUpdate is the body of POST /services/{id}/update. The update
+// * config of the service is kept when it is left out.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceUpdate {
+    pub template: Task,
+    #[serde(default)]
+    pub update_config: Option<UpdateConfig>,
+}
+
+// * ScaleRequest is the body of PUT /services/{id}/scale
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScaleRequest {
+    pub replicas: u32,
+}
+
+// * ServiceStatus is a service together with the state of its tasks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceStatus {
+    #[serde(flatten)]
+    pub service: Service,
+    pub running: u32,
+    // * Tasks of the service per state, finished ones included. Tasks whose
+    // * start is still queued count as Pending.
+    pub states: HashMap<State, u32>,
+    // * Ids of the tasks that count towards the replicas
+    pub tasks: Vec<String>,
+}
+
+// * ApplyAction is what applying a manifest did to its task
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApplyAction {
+    Created,
+    Updated,
+    Unchanged,
+}
+
+// * ApplyResult reports one manifest of a POST /apply
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApplyResult {
+    pub name: String,
+    pub task_id: String,
+    pub action: ApplyAction,
 }
 
 pub struct ManagerServer {
@@ -28,8 +315,10 @@ pub struct ManagerServer {
 #[derive(Debug, Clone, PartialEq)]
 pub enum ManagerError {
     NoWorkersAvailable,
+    WorkerNotFound(String),
     WorkerCommunication(String),
     NetworkError(String),
+    StoreError(String),
 }
 
 impl fmt::Display for ManagerError {
@@ -38,12 +327,18 @@ impl fmt::Display for ManagerError {
             ManagerError::NoWorkersAvailable => {
                 write!(f, "No workers are available to handle tasks")
             }
+            ManagerError::WorkerNotFound(name) => {
+                write!(f, "Worker {} is not registered", name)
+            }
             ManagerError::WorkerCommunication(msg) => {
                 write!(f, "Worker communication failed: {}", msg)
             }
             ManagerError::NetworkError(msg) => {
                 write!(f, "Network error: {}", msg)
             }
+            ManagerError::StoreError(msg) => {
+                write!(f, "Store error: {}", msg)
+            }
         }
     }
 }
@@ -54,4 +349,10 @@ impl Error for ManagerError {
     }
 }
 
+impl From<StoreError> for ManagerError {
+    fn from(error: StoreError) -> Self {
+        ManagerError::StoreError(error.to_string())
+    }
+}
+
 pub type ManagerResult<T> = Result<T, ManagerError>;
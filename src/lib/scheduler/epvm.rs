@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+
+use super::{
+    scheduler::{Scheduler, eligible_nodes, has_capacity},
+    types::{Epvm, Node},
+};
+use crate::lib::tasks::types::Task;
+
+// * Base of the exponential cost function used by E-PVM
+const LIEB: f64 = 1.53846153846;
+// * Number of jobs a node is expected to run comfortably at the same time
+const MAX_JOBS: f64 = 4.0;
+
+fn load(usage: f64, capacity: f64) -> f64 {
+    if capacity > 0.0 {
+        usage / capacity
+    } else {
+        0.0
+    }
+}
+
+impl Scheduler for Epvm {
+    fn select_candidate_nodes(&self, task: &Task, nodes: &[Node]) -> Vec<Node> {
+        eligible_nodes(task, nodes.iter().filter(|node| has_capacity(task, node)))
+    }
+
+    fn score(&self, task: &Task, nodes: &[Node]) -> HashMap<String, f64> {
+        nodes
+            .iter()
+            .map(|node| {
+                let task_count = node.task_count as f64;
+                let jobs_cost =
+                    LIEB.powf((task_count + 1.0) / MAX_JOBS) - LIEB.powf(task_count / MAX_JOBS);
+
+                let memory = node.memory as f64;
+                let memory_allocated = node.memory_allocated as f64;
+                let memory_load = load(memory_allocated, memory);
+                let new_memory_load = load(memory_allocated + task.memory as f64, memory);
+                let memory_cost = LIEB.powf(new_memory_load) - LIEB.powf(memory_load) + jobs_cost;
+
+                // The task's CPUs add to the node's measured usage, as a share
+                // of its cores
+                let cpu_load = load(node.cpu_usage, 100.0);
+                let new_cpu_load = cpu_load + load(task.cpu, node.cores as f64);
+                let cpu_cost = LIEB.powf(new_cpu_load) - LIEB.powf(cpu_load) + jobs_cost;
+
+                (node.name.clone(), memory_cost + cpu_cost)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(name: &str, task_count: u64, memory_allocated: u64, cpu_usage: f64) -> Node {
+        Node {
+            cores: 4,
+            memory: 4096,
+            memory_allocated,
+            cpu_usage,
+            task_count,
+            ..Node::new(name, name, "worker")
+        }
+    }
+
+    #[test]
+    fn epvm_prefers_the_lower_marginal_cost() {
+        let task = Task {
+            cpu: 1.0,
+            memory: 1024,
+            ..Task::default()
+        };
+        let nodes = [
+            node("busy", 2, 3072, 80.0),
+            node("idle", 2, 0, 10.0),
+            node("crowded", 6, 0, 10.0),
+        ];
+
+        let scores = Epvm.score(&task, &nodes);
+        assert!(scores["idle"] < scores["busy"]);
+        assert!(scores["idle"] < scores["crowded"]);
+        assert_eq!(
+            Epvm.pick(&scores, &nodes).map(|node| node.name).as_deref(),
+            Some("idle")
+        );
+    }
+
+    #[test]
+    fn epvm_charges_more_for_a_task_asking_for_more_cpu() {
+        let nodes = [node("w", 1, 0, 50.0)];
+        let cost = |cpu: f64| {
+            Epvm.score(
+                &Task {
+                    cpu,
+                    ..Task::default()
+                },
+                &nodes,
+            )["w"]
+        };
+
+        assert!(cost(0.0) < cost(1.0));
+        assert!(cost(1.0) < cost(2.0));
+    }
+}
@@ -0,0 +1,63 @@
+use std::collections::HashMap;
+
+use super::{
+    scheduler::{Scheduler, eligible_nodes, has_capacity},
+    types::{Greedy, Node},
+};
+use crate::lib::tasks::types::Task;
+
+impl Scheduler for Greedy {
+    fn select_candidate_nodes(&self, task: &Task, nodes: &[Node]) -> Vec<Node> {
+        eligible_nodes(task, nodes.iter().filter(|node| has_capacity(task, node)))
+    }
+
+    fn score(&self, _task: &Task, nodes: &[Node]) -> HashMap<String, f64> {
+        nodes
+            .iter()
+            .map(|node| {
+                // Task count dominates, memory pressure and CPU break ties
+                let memory_load = if node.memory > 0 {
+                    node.memory_allocated as f64 / node.memory as f64
+                } else {
+                    0.0
+                };
+                let cpu_load = node.cpu_usage / 100.0;
+                let score = node.task_count as f64 + (memory_load + cpu_load) / 2.0;
+                (node.name.clone(), score)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(name: &str, task_count: u64, memory_allocated: u64, cpu_usage: f64) -> Node {
+        Node {
+            memory: 4096,
+            memory_allocated,
+            cpu_usage,
+            task_count,
+            ..Node::new(name, name, "worker")
+        }
+    }
+
+    fn picked(nodes: &[Node]) -> Option<String> {
+        let task = Task::default();
+        let candidates = Greedy.select_candidate_nodes(&task, nodes);
+        let scores = Greedy.score(&task, &candidates);
+        Greedy.pick(&scores, &candidates).map(|node| node.name)
+    }
+
+    #[test]
+    fn greedy_picks_the_least_loaded_node() {
+        // Fewer tasks wins even against lighter memory and CPU use
+        let nodes = [node("two", 2, 0, 0.0), node("one", 1, 3072, 100.0)];
+        assert_eq!(picked(&nodes).as_deref(), Some("one"));
+
+        // Between equal task counts, memory and CPU break the tie
+        let nodes = [node("loaded", 1, 2048, 50.0), node("light", 1, 1024, 10.0)];
+        assert_eq!(picked(&nodes).as_deref(), Some("light"));
+    }
+}
@@ -0,0 +1,6 @@
+pub mod epvm;
+pub mod greedy;
+pub mod round_robin;
+#[allow(clippy::module_inception)]
+pub mod scheduler;
+pub mod types;
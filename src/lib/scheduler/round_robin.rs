@@ -0,0 +1,38 @@
+use std::{collections::HashMap, sync::atomic::Ordering};
+
+use super::{
+    scheduler::{Scheduler, eligible_nodes, has_capacity, lowest_score},
+    types::{Node, RoundRobin},
+};
+use crate::lib::tasks::types::Task;
+
+impl Scheduler for RoundRobin {
+    fn select_candidate_nodes(&self, task: &Task, nodes: &[Node]) -> Vec<Node> {
+        eligible_nodes(task, nodes.iter().filter(|node| has_capacity(task, node)))
+    }
+
+    fn score(&self, _task: &Task, nodes: &[Node]) -> HashMap<String, f64> {
+        if nodes.is_empty() {
+            return HashMap::new();
+        }
+
+        let next = self.last_worker.load(Ordering::Relaxed) % nodes.len();
+
+        nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| {
+                let score = if i == next { 0.1 } else { 1.0 };
+                (node.name.clone(), score)
+            })
+            .collect()
+    }
+
+    // * Only a pick moves the cursor on, so scoring a task without placing
+    // * it does not skip a worker
+    fn pick(&self, scores: &HashMap<String, f64>, candidates: &[Node]) -> Option<Node> {
+        let node = lowest_score(scores, candidates)?;
+        self.last_worker.fetch_add(1, Ordering::Relaxed);
+        Some(node)
+    }
+}
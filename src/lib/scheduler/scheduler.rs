@@ -1,7 +1,280 @@
-pub trait scheduler {
-    fn select_candidate_node(&self) -> void;
-    fn score(&self) -> u64;
-    fn pick(&self) -> void;
+use std::{collections::HashMap, fmt, sync::Arc};
+
+use super::types::{Epvm, Greedy, Node, RoundRobin, SchedulerType};
+use crate::lib::tasks::types::Task;
+
+// * Scores are costs: the node with the lowest score is the best placement
+pub trait Scheduler: fmt::Debug + Send + Sync {
+    fn select_candidate_nodes(&self, task: &Task, nodes: &[Node]) -> Vec<Node>;
+
+    fn score(&self, task: &Task, nodes: &[Node]) -> HashMap<String, f64>;
+
+    fn pick(&self, scores: &HashMap<String, f64>, candidates: &[Node]) -> Option<Node> {
+        lowest_score(scores, candidates)
+    }
+}
+
+pub fn new_scheduler(scheduler_type: SchedulerType) -> Arc<dyn Scheduler> {
+    match scheduler_type {
+        SchedulerType::RoundRobin => Arc::new(RoundRobin::default()),
+        SchedulerType::Greedy => Arc::new(Greedy),
+        SchedulerType::Epvm => Arc::new(Epvm),
+    }
+}
+
+// * The candidate with the lowest score
+pub fn lowest_score(scores: &HashMap<String, f64>, candidates: &[Node]) -> Option<Node> {
+    candidates
+        .iter()
+        .filter_map(|node| scores.get(&node.name).map(|score| (node, *score)))
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(node, _)| node.clone())
+}
+
+// * A capacity of zero means the node has not reported it yet, so it is not
+// * used to rule the node out
+pub fn has_capacity(task: &Task, node: &Node) -> bool {
+    let fits = |requested: u64, capacity: u64, allocated: u64| {
+        capacity == 0 || requested <= capacity.saturating_sub(allocated)
+    };
+
+    fits(task.memory, node.memory, node.memory_allocated)
+        && fits(task.disk, node.disk, node.disk_allocated)
+}
+
+// * Whether `labels` match a "key=value" or "key" selector
+pub fn matches_selector(selector: &str, labels: &HashMap<String, String>) -> bool {
+    match selector.split_once('=') {
+        Some((key, value)) => labels.get(key).is_some_and(|label| label == value),
+        None => labels.contains_key(selector),
+    }
+}
+
+// * Whether the node's labels meet the task's required and forbidden
+// * selectors
+pub fn satisfies_constraints(task: &Task, node: &Node) -> bool {
+    let placement = &task.placement;
+    placement
+        .required
+        .iter()
+        .all(|selector| matches_selector(selector, &node.labels))
+        && !placement
+            .forbidden
+            .iter()
+            .any(|selector| matches_selector(selector, &node.labels))
+}
+
+// * How much the task's affinity rules dislike the node: every task on it
+// * matching an anti-affinity selector adds one, every task matching an
+// * affinity selector takes one off
+pub fn affinity_cost(task: &Task, node: &Node) -> i64 {
+    let placement = &task.placement;
+    let count = |selectors: &[String]| {
+        node.task_labels
+            .iter()
+            .filter(|labels| {
+                selectors
+                    .iter()
+                    .any(|selector| matches_selector(selector, labels))
+            })
+            .count() as i64
+    };
+    count(&placement.anti_affinity) - count(&placement.affinity)
+}
+
+// * Narrows `nodes` down to the ones the task may run on, then to the ones
+// * its affinity rules like best. Affinity is soft, so it never rules out
+// * every node that meets the constraints.
+pub fn eligible_nodes<'a>(task: &Task, nodes: impl IntoIterator<Item = &'a Node>) -> Vec<Node> {
+    let allowed: Vec<&Node> = nodes
+        .into_iter()
+        .filter(|node| satisfies_constraints(task, node))
+        .collect();
+    let Some(best) = allowed.iter().map(|node| affinity_cost(task, node)).min() else {
+        return Vec::new();
+    };
+
+    allowed
+        .into_iter()
+        .filter(|node| affinity_cost(task, node) == best)
+        .cloned()
+        .collect()
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lib::tasks::types::Placement;
+
+    fn labels(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn node(name: &str, node_labels: &[(&str, &str)], task_labels: &[&[(&str, &str)]]) -> Node {
+        Node {
+            labels: labels(node_labels),
+            task_labels: task_labels.iter().map(|pairs| labels(pairs)).collect(),
+            ..Node::new(name, name, "worker")
+        }
+    }
+
+    fn task(placement: Placement) -> Task {
+        Task {
+            placement,
+            ..Task::default()
+        }
+    }
+
+    fn selectors(selectors: &[&str]) -> Vec<String> {
+        selectors.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn matches_selector_compares_values_or_only_keys() {
+        let worker = labels(&[("zone", "a"), ("gpu", "")]);
+
+        assert!(matches_selector("zone=a", &worker));
+        assert!(!matches_selector("zone=b", &worker));
+        assert!(matches_selector("zone", &worker));
+        assert!(matches_selector("gpu", &worker));
+        assert!(matches_selector("gpu=", &worker));
+        assert!(!matches_selector("ssd", &worker));
+    }
+
+    #[test]
+    fn satisfies_constraints_needs_every_required_and_no_forbidden_selector() {
+        let worker = node("w", &[("arch", "x86_64"), ("zone", "a")], &[]);
+        let placed = |required: &[&str], forbidden: &[&str]| {
+            satisfies_constraints(
+                &task(Placement {
+                    required: selectors(required),
+                    forbidden: selectors(forbidden),
+                    ..Placement::default()
+                }),
+                &worker,
+            )
+        };
 
+        assert!(placed(&[], &[]));
+        assert!(placed(&["arch=x86_64", "zone=a"], &[]));
+        assert!(!placed(&["arch=x86_64", "zone=b"], &[]));
+        assert!(!placed(&["zone=a"], &["arch"]));
+        assert!(placed(&["zone=a"], &["zone=b"]));
+    }
+
+    #[test]
+    fn affinity_cost_counts_matching_tasks() {
+        let worker = node(
+            "w",
+            &[],
+            &[&[("app", "web")], &[("app", "web")], &[("app", "db")]],
+        );
+        let cost = |affinity: &[&str], anti_affinity: &[&str]| {
+            affinity_cost(
+                &task(Placement {
+                    affinity: selectors(affinity),
+                    anti_affinity: selectors(anti_affinity),
+                    ..Placement::default()
+                }),
+                &worker,
+            )
+        };
+
+        assert_eq!(cost(&[], &[]), 0);
+        assert_eq!(cost(&[], &["app=web"]), 2);
+        assert_eq!(cost(&["app=db"], &[]), -1);
+        assert_eq!(cost(&["app=db"], &["app=web"]), 1);
+        // A task matching several selectors of one list counts once
+        assert_eq!(cost(&[], &["app=web", "app"]), 3);
+    }
+
+    #[test]
+    fn eligible_nodes_filters_constraints_then_keeps_the_best_affinity() {
+        let nodes = [
+            node("a1", &[("zone", "a")], &[&[("app", "web")]]),
+            node("a2", &[("zone", "a")], &[]),
+            node("b1", &[("zone", "b")], &[]),
+        ];
+        let names = |placement: Placement| {
+            eligible_nodes(&task(placement), &nodes)
+                .into_iter()
+                .map(|node| node.name)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(names(Placement::default()), ["a1", "a2", "b1"]);
+        assert_eq!(
+            names(Placement {
+                required: selectors(&["zone=a"]),
+                anti_affinity: selectors(&["app=web"]),
+                ..Placement::default()
+            }),
+            ["a2"]
+        );
+        // Affinity is soft: nodes that all dislike the task stay eligible
+        assert_eq!(
+            names(Placement {
+                required: selectors(&["zone=a"]),
+                anti_affinity: selectors(&["zone"]),
+                ..Placement::default()
+            }),
+            ["a1", "a2"]
+        );
+        assert!(
+            names(Placement {
+                required: selectors(&["zone=c"]),
+                ..Placement::default()
+            })
+            .is_empty()
+        );
+    }
+
+    #[test]
+    fn round_robin_only_moves_on_when_it_picks() {
+        let scheduler = RoundRobin::default();
+        let nodes = [node("w1", &[], &[]), node("w2", &[], &[])];
+        let task = Task::default();
+        let pick = || {
+            let scores = scheduler.score(&task, &nodes);
+            scheduler.pick(&scores, &nodes).map(|node| node.name)
+        };
+
+        // Scoring alone leaves the cursor where it was
+        scheduler.score(&task, &nodes);
+        assert_eq!(pick().as_deref(), Some("w1"));
+        assert_eq!(pick().as_deref(), Some("w2"));
+        assert_eq!(pick().as_deref(), Some("w1"));
+        assert!(scheduler.pick(&HashMap::new(), &nodes).is_none());
+        assert_eq!(pick().as_deref(), Some("w2"));
+    }
+
+    #[test]
+    fn round_robin_only_places_on_a_node_with_capacity() {
+        let scheduler = RoundRobin::default();
+        let full = Node {
+            memory: 1024,
+            memory_allocated: 1024,
+            ..node("full", &[], &[])
+        };
+        let free = Node {
+            memory: 1024,
+            memory_allocated: 0,
+            ..node("free", &[], &[])
+        };
+        let task = Task {
+            memory: 512,
+            ..Task::default()
+        };
+
+        // The cursor points at the full node, which must still be skipped
+        for _ in 0..2 {
+            let candidates = scheduler.select_candidate_nodes(&task, &[full.clone(), free.clone()]);
+            let scores = scheduler.score(&task, &candidates);
+            let picked = scheduler.pick(&scores, &candidates).map(|node| node.name);
+            assert_eq!(picked.as_deref(), Some("free"));
+        }
+    }
+}
@@ -1,3 +1,8 @@
+use std::{collections::HashMap, fmt, str::FromStr, sync::atomic::AtomicUsize};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Node {
     pub name: String,
     pub ip: String,
@@ -6,6 +11,72 @@ pub struct Node {
     pub memory_allocated: u64,
     pub disk: u64,
     pub disk_allocated: u64,
+    // * Global CPU usage of the node in percent (0-100)
+    pub cpu_usage: f64,
     pub role: String,
     pub task_count: u64,
-}
\ No newline at end of file
+    // * Labels of the worker behind the node
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+    // * Labels of every unfinished task placed on the node, which affinity
+    // * rules match
+    #[serde(default)]
+    pub task_labels: Vec<HashMap<String, String>>,
+}
+
+impl Node {
+    pub fn new(name: &str, ip: &str, role: &str) -> Self {
+        Node {
+            name: name.to_string(),
+            ip: ip.to_string(),
+            role: role.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SchedulerType {
+    #[default]
+    RoundRobin,
+    Greedy,
+    Epvm,
+}
+
+impl FromStr for SchedulerType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "roundrobin" | "round-robin" | "round_robin" => Ok(SchedulerType::RoundRobin),
+            "greedy" => Ok(SchedulerType::Greedy),
+            "epvm" => Ok(SchedulerType::Epvm),
+            other => Err(format!("Unknown scheduler type: {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for SchedulerType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchedulerType::RoundRobin => write!(f, "roundrobin"),
+            SchedulerType::Greedy => write!(f, "greedy"),
+            SchedulerType::Epvm => write!(f, "epvm"),
+        }
+    }
+}
+
+// * RoundRobin hands tasks to every node in turn, ignoring their load
+#[derive(Debug, Default)]
+pub struct RoundRobin {
+    pub last_worker: AtomicUsize,
+}
+
+// * Greedy places each task on the least loaded node
+#[derive(Debug, Default)]
+pub struct Greedy;
+
+// * Epvm scores nodes with the E-PVM (enhanced parallel virtual machine)
+// * marginal cost of adding the task to their CPU and memory load
+#[derive(Debug, Default)]
+pub struct Epvm;
@@ -0,0 +1,336 @@
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use serde::{Serialize, de::DeserializeOwned};
+
+use super::{
+    store::Store,
+    types::{FileLog, FileStore, LogEntry, StoreError, StoreResult},
+};
+
+fn io_error(path: &Path, e: std::io::Error) -> StoreError {
+    StoreError::Io(format!("{}: {}", path.display(), e))
+}
+
+// * The log is compacted once it holds at least this many dead entries and
+// * more dead entries than live ones
+const COMPACT_MIN_DEAD: usize = 1000;
+// * How long an append may sit in the page cache before it is synced. A
+// * crashed process loses nothing, a crashed machine at most this much.
+const SYNC_INTERVAL: Duration = Duration::from_secs(1);
+
+fn write_entry<T: Serialize>(file: &mut File, entry: &LogEntry<T>) -> StoreResult<()> {
+    let mut line =
+        serde_json::to_string(entry).map_err(|e| StoreError::Serialization(e.to_string()))?;
+    line.push('\n');
+    file.write_all(line.as_bytes())
+        .map_err(|e| StoreError::Io(e.to_string()))
+}
+
+// * Replaces the log at `path` with one entry per record and returns it
+// * opened for appending
+fn write_log<T: Serialize + Clone>(path: &Path, items: &HashMap<String, T>) -> StoreResult<File> {
+    let compacted = path.with_extension("compact");
+    {
+        let mut file = File::create(&compacted).map_err(|e| io_error(&compacted, e))?;
+        for (key, value) in items {
+            write_entry(
+                &mut file,
+                &LogEntry::Put {
+                    key: key.clone(),
+                    value: value.clone(),
+                },
+            )?;
+        }
+        file.sync_data().map_err(|e| io_error(&compacted, e))?;
+    }
+    fs::rename(&compacted, path).map_err(|e| io_error(path, e))?;
+
+    OpenOptions::new()
+        .append(true)
+        .open(path)
+        .map_err(|e| io_error(path, e))
+}
+
+impl<T: Serialize + Clone> FileLog<T> {
+    // * Appends `entry`, which makes `dead` earlier entries obsolete
+    fn append(&mut self, entry: &LogEntry<T>, dead: usize) -> StoreResult<()> {
+        write_entry(&mut self.file, entry)?;
+        self.unsynced = true;
+        self.dead += dead;
+        if self.last_sync.elapsed() >= SYNC_INTERVAL {
+            self.sync()?;
+        }
+        Ok(())
+    }
+
+    // * Rewrites the log once most of it is dead. Called after `items`
+    // * took the change in, so the rewrite includes it.
+    fn compact_if_needed(&mut self) -> StoreResult<()> {
+        if self.dead >= COMPACT_MIN_DEAD && self.dead > self.items.len() {
+            self.file = write_log(&self.path, &self.items)?;
+            self.dead = 0;
+            self.unsynced = false;
+            self.last_sync = Instant::now();
+        }
+        Ok(())
+    }
+}
+
+impl<T> FileLog<T> {
+    fn sync(&mut self) -> StoreResult<()> {
+        self.file.sync_data().map_err(|e| io_error(&self.path, e))?;
+        self.unsynced = false;
+        self.last_sync = Instant::now();
+        Ok(())
+    }
+}
+
+impl<T> Drop for FileLog<T> {
+    fn drop(&mut self) {
+        if self.unsynced
+            && let Err(e) = self.sync()
+        {
+            eprintln!("Error syncing store log: {}", e);
+        }
+    }
+}
+
+impl<T: Serialize + DeserializeOwned + Clone> FileStore<T> {
+    // * Opens the log at `path`, creating it if needed. The existing log is
+    // * replayed and rewritten with one entry per live record.
+    pub fn open(path: impl Into<PathBuf>) -> StoreResult<Self> {
+        let path = path.into();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| io_error(parent, e))?;
+        }
+
+        let items = Self::replay(&path)?;
+        let file = write_log(&path, &items)?;
+
+        Ok(FileStore {
+            state: Mutex::new(FileLog {
+                items,
+                file,
+                path,
+                dead: 0,
+                last_sync: Instant::now(),
+                unsynced: false,
+            }),
+        })
+    }
+
+    fn replay(path: &Path) -> StoreResult<HashMap<String, T>> {
+        let mut items = HashMap::new();
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(items),
+            Err(e) => return Err(io_error(path, e)),
+        };
+
+        let mut lines = BufReader::new(file).lines().enumerate().peekable();
+        while let Some((number, line)) = lines.next() {
+            let line = line.map_err(|e| io_error(path, e))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            match serde_json::from_str::<LogEntry<T>>(&line) {
+                Ok(LogEntry::Put { key, value }) => {
+                    items.insert(key, value);
+                }
+                Ok(LogEntry::Delete { key }) => {
+                    items.remove(&key);
+                }
+                // A crash can leave a partially written last line behind
+                Err(e) if lines.peek().is_none() => eprintln!(
+                    "Skipping torn last entry on line {} of {}: {}",
+                    number + 1,
+                    path.display(),
+                    e
+                ),
+                // Anything earlier means the log is damaged
+                Err(e) => {
+                    return Err(StoreError::Serialization(format!(
+                        "{}: unreadable entry on line {}: {}",
+                        path.display(),
+                        number + 1,
+                        e
+                    )));
+                }
+            }
+        }
+
+        Ok(items)
+    }
+}
+
+impl<T: Serialize + DeserializeOwned + Clone + Debug + Send> Store<T> for FileStore<T> {
+    fn put(&self, key: &str, value: T) -> StoreResult<()> {
+        let mut state = self.state.lock().unwrap();
+        let entry = LogEntry::Put {
+            key: key.to_string(),
+            value,
+        };
+        let replaced = usize::from(state.items.contains_key(key));
+        state.append(&entry, replaced)?;
+        if let LogEntry::Put { key, value } = entry {
+            state.items.insert(key, value);
+        }
+        state.compact_if_needed()
+    }
+
+    fn get(&self, key: &str) -> StoreResult<Option<T>> {
+        Ok(self.state.lock().unwrap().items.get(key).cloned())
+    }
+
+    fn entries(&self) -> StoreResult<Vec<(String, T)>> {
+        Ok(self
+            .state
+            .lock()
+            .unwrap()
+            .items
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect())
+    }
+
+    fn delete(&self, key: &str) -> StoreResult<()> {
+        let mut state = self.state.lock().unwrap();
+        if state.items.contains_key(key) {
+            // Both the put it removes and the delete itself are dead
+            state.append(
+                &LogEntry::Delete {
+                    key: key.to_string(),
+                },
+                2,
+            )?;
+            state.items.remove(key);
+            state.compact_if_needed()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use super::*;
+
+    fn log_path() -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("r_cube-store-{}", uuid::Uuid::new_v4()))
+            .join("items.log")
+    }
+
+    #[test]
+    fn reopening_replays_puts_and_deletes() {
+        let path = log_path();
+        {
+            let store = FileStore::<u32>::open(&path).unwrap();
+            store.put("a", 1).unwrap();
+            store.put("b", 2).unwrap();
+            store.put("a", 3).unwrap();
+            store.delete("b").unwrap();
+        }
+
+        let store = FileStore::<u32>::open(&path).unwrap();
+        assert_eq!(store.get("a").unwrap(), Some(3));
+        assert_eq!(store.get("b").unwrap(), None);
+        assert_eq!(store.list().unwrap(), vec![3]);
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn opening_compacts_the_log() {
+        let path = log_path();
+        {
+            let store = FileStore::<u32>::open(&path).unwrap();
+            for value in 0..5 {
+                store.put("a", value).unwrap();
+            }
+            store.put("b", 1).unwrap();
+            store.delete("b").unwrap();
+        }
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 7);
+
+        FileStore::<u32>::open(&path).unwrap();
+        let log = fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            log.lines().collect::<Vec<_>>(),
+            [r#"{"op":"put","key":"a","value":4}"#]
+        );
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn a_torn_last_line_is_skipped() {
+        let path = log_path();
+        {
+            let store = FileStore::<u32>::open(&path).unwrap();
+            store.put("a", 1).unwrap();
+        }
+        let mut log = fs::read_to_string(&path).unwrap();
+        log.push_str(r#"{"op":"put","key":"b","va"#);
+        fs::write(&path, log).unwrap();
+
+        let store = FileStore::<u32>::open(&path).unwrap();
+        assert_eq!(store.get("a").unwrap(), Some(1));
+        assert_eq!(store.get("b").unwrap(), None);
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn the_log_is_compacted_once_most_of_it_is_dead() {
+        let path = log_path();
+        let store = FileStore::<usize>::open(&path).unwrap();
+        store.put("b", 0).unwrap();
+        // The first put of "a" is live, every later one kills the one before
+        for value in 0..=COMPACT_MIN_DEAD {
+            store.put("a", value).unwrap();
+        }
+
+        let log = fs::read_to_string(&path).unwrap();
+        assert_eq!(log.lines().count(), 2);
+        assert!(log.contains(&format!(r#""value":{}"#, COMPACT_MIN_DEAD)));
+
+        store.put("a", 0).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 3);
+        drop(store);
+
+        let store = FileStore::<usize>::open(&path).unwrap();
+        assert_eq!(store.get("a").unwrap(), Some(0));
+        assert_eq!(store.get("b").unwrap(), Some(0));
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn an_unreadable_line_before_the_last_is_an_error() {
+        let path = log_path();
+        {
+            let store = FileStore::<u32>::open(&path).unwrap();
+            store.put("a", 1).unwrap();
+        }
+        let log = fs::read_to_string(&path).unwrap();
+        fs::write(&path, format!("{{\"op\":\"put\"\n{}", log)).unwrap();
+
+        let error = FileStore::<u32>::open(&path).unwrap_err();
+        assert!(matches!(error, StoreError::Serialization(message) if message.contains("line 1")));
+        // The damaged log is left as it was
+        assert!(fs::read_to_string(&path).unwrap().ends_with(&log));
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
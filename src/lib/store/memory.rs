@@ -0,0 +1,46 @@
+use std::{collections::HashMap, fmt::Debug, sync::Mutex};
+
+use super::{
+    store::Store,
+    types::{InMemoryStore, StoreResult},
+};
+
+impl<T> InMemoryStore<T> {
+    pub fn new() -> Self {
+        InMemoryStore {
+            items: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<T> Default for InMemoryStore<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone + Debug + Send> Store<T> for InMemoryStore<T> {
+    fn put(&self, key: &str, value: T) -> StoreResult<()> {
+        self.items.lock().unwrap().insert(key.to_string(), value);
+        Ok(())
+    }
+
+    fn get(&self, key: &str) -> StoreResult<Option<T>> {
+        Ok(self.items.lock().unwrap().get(key).cloned())
+    }
+
+    fn entries(&self) -> StoreResult<Vec<(String, T)>> {
+        Ok(self
+            .items
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect())
+    }
+
+    fn delete(&self, key: &str) -> StoreResult<()> {
+        self.items.lock().unwrap().remove(key);
+        Ok(())
+    }
+}
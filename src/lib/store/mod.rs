@@ -0,0 +1,5 @@
+pub mod file;
+pub mod memory;
+#[allow(clippy::module_inception)]
+pub mod store;
+pub mod types;
@@ -0,0 +1,23 @@
+use std::fmt::Debug;
+
+use super::types::StoreResult;
+
+// * Store is a keyed collection of records. Implementations use interior
+// * mutability so a store can be shared between tasks behind an Arc.
+pub trait Store<T>: Debug + Send + Sync {
+    fn put(&self, key: &str, value: T) -> StoreResult<()>;
+
+    fn get(&self, key: &str) -> StoreResult<Option<T>>;
+
+    fn entries(&self) -> StoreResult<Vec<(String, T)>>;
+
+    fn delete(&self, key: &str) -> StoreResult<()>;
+
+    fn list(&self) -> StoreResult<Vec<T>> {
+        Ok(self
+            .entries()?
+            .into_iter()
+            .map(|(_, value)| value)
+            .collect())
+    }
+}
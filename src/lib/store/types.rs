@@ -0,0 +1,57 @@
+use std::{
+    collections::HashMap, error::Error, fmt, fs::File, path::PathBuf, sync::Mutex, time::Instant,
+};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug)]
+pub struct InMemoryStore<T> {
+    pub items: Mutex<HashMap<String, T>>,
+}
+
+// * FileStore keeps every record in memory and appends each change to a JSON
+// * lines log, which is replayed when the store is opened and compacted once
+// * it holds more dead entries than live ones
+#[derive(Debug)]
+pub struct FileStore<T> {
+    pub state: Mutex<FileLog<T>>,
+}
+
+#[derive(Debug)]
+pub struct FileLog<T> {
+    pub items: HashMap<String, T>,
+    pub file: File,
+    pub path: PathBuf,
+    // * Entries in the log that a later one replaced or deleted
+    pub dead: usize,
+    // * Appends are flushed to disk at most once per sync interval
+    pub last_sync: Instant,
+    pub unsynced: bool,
+}
+
+// * LogEntry is one line of a FileStore log
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum LogEntry<T> {
+    Put { key: String, value: T },
+    Delete { key: String },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StoreError {
+    Io(String),
+    Serialization(String),
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::Io(msg) => write!(f, "Store I/O error: {}", msg),
+            StoreError::Serialization(msg) => write!(f, "Store serialization error: {}", msg),
+        }
+    }
+}
+
+impl Error for StoreError {}
+
+pub type StoreResult<T> = Result<T, StoreError>;
@@ -1,86 +1,125 @@
-use super::types::{Config, DockerClient};
-use crate::lib::tasks::types::{DockerError, DockerResponse, DockerResult};
+use super::logs::split_lines;
+use super::runtime::Runtime;
+use super::types::{
+    Config, ContainerInspect, ContainerStatus, DockerClient, ExecSession, ImageInfo, LogOptions,
+    LogStream, OutputStream, PortBinding, PullEvent, PullStream,
+};
+use crate::lib::tasks::types::{DockerError, RuntimeResult};
 use bollard::{
     Docker,
-    container::{CreateContainerOptions, StartContainerOptions},
-    image::CreateImageOptions,
-    secret::{HostConfig, Resources, RestartPolicy, RestartPolicyNameEnum},
+    auth::DockerCredentials,
+    container::{
+        CreateContainerOptions, KillContainerOptions, LogOutput, LogsOptions,
+        RemoveContainerOptions, StartContainerOptions,
+    },
+    exec::{CreateExecOptions, StartExecResults},
+    image::{CreateImageOptions, ListImagesOptions},
+    secret::{
+        ContainerStateStatusEnum, HostConfig, PortBinding as DockerPortBinding, Resources,
+        RestartPolicy, RestartPolicyNameEnum,
+    },
 };
 use error_stack::Report;
 use futures_util::stream::StreamExt;
-use std::{error::Error, io::Write};
+use std::{collections::HashMap, path::Path, time::SystemTime};
 
 impl DockerClient {
-    pub fn new(config: Config) -> Option<Self> {
+    pub fn new() -> Option<Self> {
         let docker_client = Docker::connect_with_unix_defaults().ok()?;
         Some(DockerClient {
             client: docker_client,
-            config,
+            credentials: HashMap::new(),
         })
     }
 
-    async fn pull_image(&self) -> Result<(), Box<dyn Error>> {
-        println!("Pulling image: {}", self.config.image);
-        let mut stream = self.client.create_image(
-            Some(CreateImageOptions {
-                from_image: self.config.image.clone(),
-                ..Default::default()
-            }),
-            None,
-            None,
+    // * Loads registry credentials from a JSON file that maps registry hosts
+    // * to a username and password or an identity token
+    pub fn with_credentials_file(mut self, path: &Path) -> RuntimeResult<Self> {
+        let invalid = |e: String| {
+            Report::new(DockerError::ClientError(format!(
+                "Invalid registry credentials file {}: {}",
+                path.display(),
+                e
+            )))
+        };
+        let contents = std::fs::read_to_string(path).map_err(|e| invalid(e.to_string()))?;
+        self.credentials = serde_json::from_str(&contents).map_err(|e| invalid(e.to_string()))?;
+        println!(
+            "Loaded credentials for {} registr(y/ies)",
+            self.credentials.len()
         );
+        Ok(self)
+    }
 
-        while let Some(msg) = stream.next().await {
-            match msg {
-                Ok(info) => {
-                    if let Some(status) = info.status {
-                        print!("\r{}", status);
-                        std::io::stdout().flush().unwrap();
-                    }
-                }
-                Err(e) => {
-                    eprintln!("\nError during image pull stream: {:?}", e);
-                    return Err(Box::new(e));
-                }
-            }
-        }
-
-        println!("\nImage pulled: {}", self.config.image);
-        Ok(())
+    fn credentials_for(&self, image: &str) -> Option<DockerCredentials> {
+        let registry = registry_host(image);
+        self.credentials
+            .get(registry)
+            .map(|credentials| DockerCredentials {
+                username: credentials.username.clone(),
+                password: credentials.password.clone(),
+                identitytoken: credentials.identity_token.clone(),
+                serveraddress: Some(registry.to_string()),
+                ..Default::default()
+            })
     }
 
-    fn host_config(&self) -> HostConfig {
+    fn host_config(config: &Config) -> HostConfig {
+        // The manager applies the task's restart policy itself, so a restart
+        // can land on another worker
         let restart_policy = RestartPolicy {
-            name: Some(
-                self.config
-                    .restart_policy
-                    .parse()
-                    .unwrap_or_else(|_| RestartPolicyNameEnum::NO),
-            ),
+            name: Some(RestartPolicyNameEnum::NO),
             maximum_retry_count: None,
         };
 
         let resources = Resources {
-            memory: Some(self.config.memory),
-            nano_cpus: Some((self.config.cpu * 1_000_000_000.0) as i64),
+            memory: Some(config.memory),
+            nano_cpus: Some((config.cpu * 1_000_000_000.0) as i64),
             ..Default::default()
         };
 
+        let mut port_bindings: HashMap<String, Option<Vec<DockerPortBinding>>> = HashMap::new();
+        for binding in &config.port_bindings {
+            port_bindings
+                .entry(binding.port_key())
+                .or_default()
+                .get_or_insert_with(Vec::new)
+                .push(DockerPortBinding {
+                    host_ip: binding.host_ip.clone(),
+                    // An empty host port lets Docker pick a free one
+                    host_port: Some(
+                        binding
+                            .host_port
+                            .map(|port| port.to_string())
+                            .unwrap_or_default(),
+                    ),
+                });
+        }
+
         HostConfig {
             restart_policy: Some(restart_policy),
             nano_cpus: resources.nano_cpus,
             memory: resources.memory,
-            publish_all_ports: Some(true),
+            // Explicit bindings replace publishing every exposed port on a
+            // random host port
+            publish_all_ports: Some(port_bindings.is_empty()),
+            port_bindings: Some(port_bindings),
             ..Default::default()
         }
     }
 
-    fn container_config(&self) -> bollard::container::Config<String> {
+    fn container_config(config: &Config) -> bollard::container::Config<String> {
+        let non_empty = |value: &str| (!value.is_empty()).then(|| value.to_string());
+
         bollard::container::Config {
-            image: Some(self.config.image.clone()),
-            env: Some(self.config.env.clone()),
+            image: Some(config.image.clone()),
+            entrypoint: (!config.entrypoint.is_empty()).then(|| config.entrypoint.clone()),
+            cmd: (!config.cmd.is_empty()).then(|| config.cmd.clone()),
+            env: Some(config.env.clone()),
+            working_dir: non_empty(&config.working_dir),
+            user: non_empty(&config.user),
             exposed_ports: Some(
-                self.config
+                config
                     .exposed_ports
                     .clone()
                     .into_iter()
@@ -92,71 +131,146 @@ impl DockerClient {
                     })
                     .collect(),
             ),
-            host_config: Some(self.host_config()),
+            host_config: Some(Self::host_config(config)),
             ..Default::default()
         }
     }
+}
+
+// * Registry an image reference points at. Like Docker, the first path
+// * component only names a registry if it looks like a host.
+fn registry_host(image: &str) -> &str {
+    match image.split_once('/') {
+        Some((host, _)) if host.contains(['.', ':']) || host == "localhost" => host,
+        _ => "docker.io",
+    }
+}
+
+impl Runtime for DockerClient {
+    async fn pull(&self, config: &Config) -> RuntimeResult<PullStream> {
+        println!("Pulling image: {}", config.image);
+        let stream = self.client.create_image(
+            Some(CreateImageOptions {
+                from_image: config.image.clone(),
+                ..Default::default()
+            }),
+            None,
+            self.credentials_for(&config.image),
+        );
+
+        let image = config.image.clone();
+        let events = stream.map(move |message| {
+            let pull_error = |e: String| {
+                Report::new(DockerError::ImagePullError(format!(
+                    "Failed to pull {}: {}",
+                    image, e
+                )))
+            };
+            let info = message.map_err(|e| pull_error(e.to_string()))?;
+            if let Some(error) = info.error {
+                return Err(pull_error(error));
+            }
+
+            let progress = info.progress_detail.unwrap_or_default();
+            Ok(PullEvent {
+                timestamp: SystemTime::now(),
+                status: info.status.unwrap_or_default(),
+                layer: info.id,
+                current: progress.current,
+                total: progress.total,
+            })
+        });
+        Ok(Box::pin(events))
+    }
 
-    pub async fn run(&self) -> DockerResult {
-        if let Err(e) = self.pull_image().await {
-            return Err(Report::new(DockerError::ImagePullError(e.to_string())));
+    async fn has_image(&self, image: &str) -> RuntimeResult<bool> {
+        match self.client.inspect_image(image).await {
+            Ok(_) => Ok(true),
+            Err(bollard::errors::Error::DockerResponseServerError {
+                status_code: 404, ..
+            }) => Ok(false),
+            Err(e) => Err(Report::new(DockerError::ImageListError(format!(
+                "Failed to inspect image {}: {}",
+                image, e
+            )))),
         }
+    }
 
+    async fn images(&self) -> RuntimeResult<Vec<ImageInfo>> {
+        let images = self
+            .client
+            .list_images(Some(ListImagesOptions::<String> {
+                all: false,
+                ..Default::default()
+            }))
+            .await
+            .map_err(|e| {
+                Report::new(DockerError::ImageListError(format!(
+                    "Failed to list images: {}",
+                    e
+                )))
+            })?;
+
+        Ok(images
+            .into_iter()
+            .map(|image| ImageInfo {
+                id: image.id,
+                tags: image.repo_tags,
+                size: image.size,
+                created: image.created,
+            })
+            .collect())
+    }
+
+    async fn create(&self, config: &Config) -> RuntimeResult<String> {
         let options = Some(CreateContainerOptions {
-            name: self.config.name.replace(' ', "-"),
+            name: config.name.clone(),
             ..Default::default()
         });
 
-        let container_id = match self
+        match self
             .client
-            .create_container(options, self.container_config())
+            .create_container(options, Self::container_config(config))
             .await
         {
             Ok(resp) => {
                 println!("Container created successfully: {}", resp.id);
-                resp.id
+                Ok(resp.id)
             }
             Err(e) => {
                 eprintln!("Error creating container: {:?}", e);
-                return Err(Report::new(DockerError::ContainerCreationError(format!(
+                Err(Report::new(DockerError::ContainerCreationError(format!(
                     "Failed to create container: {}",
                     e
-                ))));
+                ))))
             }
-        };
+        }
+    }
 
+    async fn start(&self, container_id: &str) -> RuntimeResult<()> {
         println!("Starting container: {}", container_id);
 
         if let Err(e) = self
             .client
-            .start_container(&container_id, None::<StartContainerOptions<String>>)
+            .start_container(container_id, None::<StartContainerOptions<String>>)
             .await
         {
-            eprintln!("Error starting container {}: {:?}", self.config.name, e);
+            eprintln!("Error starting container {}: {:?}", container_id, e);
             return Err(Report::new(DockerError::ContainerStartError(format!(
                 "Failed to start container: {}",
                 e
             ))));
         }
 
-        println!("Container {} started successfully.", self.config.name);
-        Ok(DockerResponse {
-            error: None,
-            action: Some("Start".to_string()),
-            container_id: Some(container_id),
-        })
+        Ok(())
     }
 
-    pub async fn stop(&self, container_id: &str) -> DockerResult {
+    async fn stop(&self, container_id: &str) -> RuntimeResult<()> {
         println!("Stopping container: {}", container_id);
         match self.client.stop_container(container_id, None).await {
             Ok(_) => {
                 println!("Container stopped successfully: {}", container_id);
-                Ok(DockerResponse {
-                    error: None,
-                    action: Some("Stop".to_string()),
-                    container_id: Some(container_id.to_string()),
-                })
+                Ok(())
             }
             Err(e) => {
                 eprintln!("Error stopping container {}: {:?}", container_id, e);
@@ -167,4 +281,207 @@ impl DockerClient {
             }
         }
     }
+
+    async fn inspect(&self, container_id: &str) -> RuntimeResult<ContainerInspect> {
+        let response = self
+            .client
+            .inspect_container(container_id, None)
+            .await
+            .map_err(|e| {
+                Report::new(DockerError::ContainerInspectError(format!(
+                    "Failed to inspect container {}: {}",
+                    container_id, e
+                )))
+            })?;
+
+        let ports = response
+            .network_settings
+            .and_then(|settings| settings.ports)
+            .unwrap_or_default()
+            .into_iter()
+            .flat_map(|(key, bindings)| {
+                bindings
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter_map(move |binding| {
+                        let mut port = PortBinding::from_port_key(&key)?;
+                        port.host_ip = binding.host_ip.filter(|ip| !ip.is_empty());
+                        port.host_port = binding.host_port.and_then(|p| p.parse().ok());
+                        Some(port)
+                    })
+            })
+            .collect();
+
+        let state = response.state.unwrap_or_default();
+        let status = match state.status {
+            Some(ContainerStateStatusEnum::CREATED) => ContainerStatus::Created,
+            Some(ContainerStateStatusEnum::RUNNING)
+            | Some(ContainerStateStatusEnum::RESTARTING)
+            | Some(ContainerStateStatusEnum::PAUSED) => ContainerStatus::Running,
+            Some(ContainerStateStatusEnum::EXITED) | Some(ContainerStateStatusEnum::DEAD) => {
+                ContainerStatus::Exited
+            }
+            _ => ContainerStatus::Unknown,
+        };
+
+        Ok(ContainerInspect {
+            exit_code: match status {
+                ContainerStatus::Exited => state.exit_code,
+                _ => None,
+            },
+            status,
+            ports,
+        })
+    }
+
+    async fn remove(&self, container_id: &str) -> RuntimeResult<()> {
+        println!("Removing container: {}", container_id);
+        self.client
+            .remove_container(
+                container_id,
+                Some(RemoveContainerOptions {
+                    force: true,
+                    ..Default::default()
+                }),
+            )
+            .await
+            .map_err(|e| {
+                Report::new(DockerError::ContainerRemoveError(format!(
+                    "Failed to remove container {}: {}",
+                    container_id, e
+                )))
+            })
+    }
+
+    async fn logs(&self, container_id: &str, options: &LogOptions) -> RuntimeResult<LogStream> {
+        let output = self.client.logs(
+            container_id,
+            Some(LogsOptions {
+                follow: options.follow,
+                stdout: options.stdout,
+                stderr: options.stderr,
+                since: options.since.unwrap_or_default() as i64,
+                until: 0,
+                timestamps: false,
+                tail: options
+                    .tail
+                    .map(|tail| tail.to_string())
+                    .unwrap_or_else(|| "all".to_string()),
+            }),
+        );
+
+        let container_id = container_id.to_string();
+        let lines = output.flat_map(move |output| {
+            let lines = match output {
+                Ok(LogOutput::StdOut { message }) | Ok(LogOutput::Console { message }) => {
+                    split_lines(OutputStream::Stdout, &message)
+                        .into_iter()
+                        .map(Ok)
+                        .collect()
+                }
+                Ok(LogOutput::StdErr { message }) => split_lines(OutputStream::Stderr, &message)
+                    .into_iter()
+                    .map(Ok)
+                    .collect(),
+                Ok(LogOutput::StdIn { .. }) => Vec::new(),
+                Err(e) => vec![Err(Report::new(DockerError::ContainerLogsError(format!(
+                    "Failed to read logs of container {}: {}",
+                    container_id, e
+                ))))],
+            };
+            futures_util::stream::iter(lines)
+        });
+        Ok(Box::pin(lines))
+    }
+
+    async fn kill(&self, container_id: &str, signal: &str) -> RuntimeResult<()> {
+        println!("Sending {} to container: {}", signal, container_id);
+        self.client
+            .kill_container(container_id, Some(KillContainerOptions { signal }))
+            .await
+            .map_err(|e| {
+                Report::new(DockerError::ContainerKillError(format!(
+                    "Failed to send {} to container {}: {}",
+                    signal, container_id, e
+                )))
+            })
+    }
+
+    async fn exec_session(
+        &self,
+        container_id: &str,
+        command: &[String],
+        stdin: bool,
+    ) -> RuntimeResult<ExecSession> {
+        let exec_error = |e: bollard::errors::Error| {
+            Report::new(DockerError::ContainerExecError(format!(
+                "Failed to exec {:?} in container {}: {}",
+                command, container_id, e
+            )))
+        };
+
+        let exec = self
+            .client
+            .create_exec(
+                container_id,
+                CreateExecOptions {
+                    cmd: Some(command.to_vec()),
+                    attach_stdin: Some(stdin),
+                    attach_stdout: Some(true),
+                    attach_stderr: Some(true),
+                    ..Default::default()
+                },
+            )
+            .await
+            .map_err(exec_error)?;
+
+        let StartExecResults::Attached { output, input } = self
+            .client
+            .start_exec(&exec.id, None)
+            .await
+            .map_err(exec_error)?
+        else {
+            return Err(Report::new(DockerError::ContainerExecError(format!(
+                "Exec of {:?} in container {} was detached",
+                command, container_id
+            ))));
+        };
+
+        let output_error = format!("Failed to read output of {:?}", command);
+        let output = output.filter_map(move |chunk| {
+            let chunk = match chunk {
+                Ok(LogOutput::StdOut { message }) | Ok(LogOutput::Console { message }) => {
+                    Some(Ok((OutputStream::Stdout, message.to_vec())))
+                }
+                Ok(LogOutput::StdErr { message }) => {
+                    Some(Ok((OutputStream::Stderr, message.to_vec())))
+                }
+                Ok(LogOutput::StdIn { .. }) => None,
+                Err(e) => Some(Err(Report::new(DockerError::ContainerExecError(format!(
+                    "{}: {}",
+                    output_error, e
+                ))))),
+            };
+            std::future::ready(chunk)
+        });
+
+        // The exit code is only known once the output has been drained
+        let client = self.client.clone();
+        let command = command.to_vec();
+        let exit = async move {
+            let inspect = client.inspect_exec(&exec.id).await.map_err(|e| {
+                Report::new(DockerError::ContainerExecError(format!(
+                    "Failed to inspect exec of {:?}: {}",
+                    command, e
+                )))
+            })?;
+            Ok(inspect.exit_code.unwrap_or(-1))
+        };
+
+        Ok(ExecSession {
+            input,
+            output: Box::pin(output),
+            exit: Box::pin(exit),
+        })
+    }
 }
@@ -0,0 +1,57 @@
+use std::{fmt, time::SystemTime};
+
+use super::types::{EventKind, State, Task, TaskEvent};
+
+impl TaskEvent {
+    pub fn new(kind: EventKind, task: Task) -> Self {
+        TaskEvent {
+            task_id: uuid::Uuid::new_v4().to_string(),
+            kind,
+            timestamp: Some(SystemTime::now()),
+            task,
+        }
+    }
+
+    // * Checks that the event carries what its kind needs
+    pub fn validate(&self) -> Result<(), String> {
+        if self.task.id.is_empty() {
+            return Err("The event's task has no id".to_string());
+        }
+
+        match &self.kind {
+            EventKind::Start | EventKind::Update => {
+                if self.task.image.is_empty() && self.task.command.is_empty() {
+                    return Err(format!(
+                        "Task {} needs an image or a command to run",
+                        self.task.id
+                    ));
+                }
+                if self.kind == EventKind::Start
+                    && !matches!(self.task.state, State::Pending | State::Scheduled)
+                {
+                    return Err(format!(
+                        "Task {} cannot be started from state {:?}",
+                        self.task.id, self.task.state
+                    ));
+                }
+                Ok(())
+            }
+            EventKind::Signal { signal } if signal.trim().is_empty() => {
+                Err("A signal event needs a signal".to_string())
+            }
+            EventKind::Stop | EventKind::Restart | EventKind::Signal { .. } => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for EventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EventKind::Start => write!(f, "start"),
+            EventKind::Stop => write!(f, "stop"),
+            EventKind::Restart => write!(f, "restart"),
+            EventKind::Update => write!(f, "update"),
+            EventKind::Signal { signal } => write!(f, "signal {}", signal),
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use std::time::Duration;
+
+use tokio::net::TcpStream;
+
+use super::runtime::Runtime;
+use super::types::{HealthCheck, HealthProbe, Task};
+
+impl HealthCheck {
+    pub fn default_interval() -> u64 {
+        10
+    }
+
+    pub fn default_timeout() -> u64 {
+        5
+    }
+
+    pub fn default_failure_threshold() -> u32 {
+        3
+    }
+
+    // * Runs the probe once against the task's container
+    pub async fn probe<R: Runtime>(&self, runtime: &R, task: &Task) -> Result<(), String> {
+        let timeout = Duration::from_secs(self.timeout);
+        let probe = async {
+            match &self.probe {
+                HealthProbe::Http { port, path } => {
+                    let url = format!("http://{}{}", host_address(task, *port), path);
+                    let response = reqwest::Client::new()
+                        .get(&url)
+                        .timeout(timeout)
+                        .send()
+                        .await
+                        .map_err(|e| format!("GET {} failed: {}", url, e))?;
+                    let status = response.status();
+                    if status.is_success() || status.is_redirection() {
+                        Ok(())
+                    } else {
+                        Err(format!("GET {} returned status {}", url, status.as_u16()))
+                    }
+                }
+                HealthProbe::Tcp { port } => {
+                    let address = host_address(task, *port);
+                    TcpStream::connect(&address)
+                        .await
+                        .map(|_| ())
+                        .map_err(|e| format!("Connecting to {} failed: {}", address, e))
+                }
+                HealthProbe::Exec { command } => {
+                    let container_id = task.container_id.as_deref().unwrap_or_default();
+                    match runtime.exec(container_id, command).await {
+                        Ok(output) if output.exit_code == 0 => Ok(()),
+                        Ok(output) => Err(format!(
+                            "{:?} exited with code {}",
+                            command, output.exit_code
+                        )),
+                        Err(e) => Err(e.to_string()),
+                    }
+                }
+            }
+        };
+
+        tokio::time::timeout(timeout, probe)
+            .await
+            .unwrap_or_else(|_| Err(format!("Probe timed out after {:?}", timeout)))
+    }
+}
+
+impl HealthProbe {
+    pub fn default_path() -> String {
+        "/".to_string()
+    }
+}
+
+// * Where a container port can be reached from the worker: the host port the
+// * runtime assigned for it, or the port itself when it isn't published
+fn host_address(task: &Task, port: u16) -> String {
+    let binding = task
+        .assigned_ports
+        .iter()
+        .find(|binding| binding.container_port == port);
+
+    let host = binding
+        .and_then(|binding| binding.host_ip.as_deref())
+        .filter(|ip| !matches!(*ip, "" | "0.0.0.0" | "::"))
+        .unwrap_or("127.0.0.1");
+    let port = binding
+        .and_then(|binding| binding.host_port)
+        .unwrap_or(port);
+
+    if host.contains(':') {
+        format!("[{}]:{}", host, port)
+    } else {
+        format!("{}:{}", host, port)
+    }
+}
@@ -0,0 +1,182 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, SystemTime},
+};
+
+use futures_util::stream::{self, StreamExt};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, BufReader},
+    sync::broadcast,
+};
+
+use super::types::{LogLine, LogOptions, LogStream, OutputStream, ProcessLogs};
+
+// * Lines a follower may fall behind by before it starts missing some
+const FOLLOW_CAPACITY: usize = 1024;
+
+// * Lines and bytes of output kept per process. The oldest lines are dropped
+// * beyond either.
+const LOG_CAPACITY: usize = 10_000;
+const LOG_BYTES: usize = 4 << 20;
+
+impl LogOptions {
+    pub fn wants(&self, stream: OutputStream) -> bool {
+        match stream {
+            OutputStream::Stdout => self.stdout,
+            OutputStream::Stderr => self.stderr,
+        }
+    }
+
+    fn since_time(&self) -> Option<SystemTime> {
+        self.since
+            .map(|secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+    }
+}
+
+// * Splits a chunk of output into lines, dropping the line breaks
+pub fn split_lines(stream: OutputStream, output: &[u8]) -> Vec<LogLine> {
+    String::from_utf8_lossy(output)
+        .lines()
+        .map(|line| LogLine {
+            stream,
+            line: line.to_string(),
+        })
+        .collect()
+}
+
+impl ProcessLogs {
+    // * Logs for a process whose `streams` outputs are still being read
+    pub fn new(streams: usize) -> Self {
+        ProcessLogs {
+            lines: VecDeque::new(),
+            bytes: 0,
+            live: (streams > 0).then(|| broadcast::channel(FOLLOW_CAPACITY).0),
+            open_streams: streams,
+        }
+    }
+
+    // * Keeps a line, dropping the oldest ones past the capacity
+    pub fn push(&mut self, at: SystemTime, line: LogLine) {
+        self.bytes += line.line.len();
+        self.lines.push_back((at, line));
+        while self.lines.len() > LOG_CAPACITY || self.bytes > LOG_BYTES {
+            let Some((_, dropped)) = self.lines.pop_front() else {
+                break;
+            };
+            self.bytes -= dropped.line.len();
+        }
+    }
+}
+
+// * Reads one output of a process into its logs until the process closes it
+pub async fn collect_output<T: AsyncRead + Unpin>(
+    logs: std::sync::Arc<std::sync::Mutex<ProcessLogs>>,
+    stream: OutputStream,
+    output: T,
+) {
+    let mut reader = BufReader::new(output).lines();
+    while let Ok(Some(line)) = reader.next_line().await {
+        let line = LogLine { stream, line };
+        let mut logs = logs.lock().unwrap();
+        if let Some(live) = &logs.live {
+            // Nobody following is not an error
+            let _ = live.send(line.clone());
+        }
+        logs.push(SystemTime::now(), line);
+    }
+
+    let mut logs = logs.lock().unwrap();
+    logs.open_streams = logs.open_streams.saturating_sub(1);
+    if logs.open_streams == 0 {
+        // Followers see the end of the stream once every output is closed
+        logs.live = None;
+    }
+}
+
+// * Streams the recorded lines that match the options and, when following,
+// * whatever the process prints after them
+pub fn read_logs(logs: &std::sync::Mutex<ProcessLogs>, options: &LogOptions) -> LogStream {
+    let (recorded, live) = {
+        let logs = logs.lock().unwrap();
+        let since = options.since_time();
+        let mut recorded: Vec<LogLine> = logs
+            .lines
+            .iter()
+            .filter(|(at, line)| {
+                options.wants(line.stream) && since.is_none_or(|since| *at >= since)
+            })
+            .map(|(_, line)| line.clone())
+            .collect();
+        if let Some(tail) = options.tail {
+            recorded.drain(..recorded.len().saturating_sub(tail));
+        }
+
+        // Subscribing under the lock means no line is missed or repeated
+        let live = options
+            .follow
+            .then(|| logs.live.as_ref().map(|live| live.subscribe()))
+            .flatten();
+        (recorded, live)
+    };
+
+    let recorded = stream::iter(recorded.into_iter().map(Ok));
+    let Some(live) = live else {
+        return Box::pin(recorded);
+    };
+
+    let options = options.clone();
+    let live = stream::unfold(live, |mut live| async move {
+        loop {
+            match live.recv().await {
+                Ok(line) => return Some((line, live)),
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    eprintln!("Log follower fell behind, skipped {} lines", missed);
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    })
+    .filter(move |line| std::future::ready(options.wants(line.stream)))
+    .map(Ok);
+
+    Box::pin(recorded.chain(live))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(text: &str) -> LogLine {
+        LogLine {
+            stream: OutputStream::Stdout,
+            line: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn push_drops_the_oldest_lines_past_the_line_capacity() {
+        let mut logs = ProcessLogs::new(0);
+        for i in 0..LOG_CAPACITY + 5 {
+            logs.push(SystemTime::now(), line(&i.to_string()));
+        }
+
+        assert_eq!(logs.lines.len(), LOG_CAPACITY);
+        assert_eq!(logs.lines.front().unwrap().1.line, "5");
+        assert_eq!(
+            logs.bytes,
+            logs.lines.iter().map(|(_, l)| l.line.len()).sum::<usize>()
+        );
+    }
+
+    #[test]
+    fn push_drops_the_oldest_lines_past_the_byte_capacity() {
+        let mut logs = ProcessLogs::new(0);
+        let big = "x".repeat(LOG_BYTES / 2);
+        logs.push(SystemTime::now(), line("first"));
+        logs.push(SystemTime::now(), line(&big));
+        logs.push(SystemTime::now(), line(&big));
+
+        assert_eq!(logs.lines.len(), 2);
+        assert_eq!(logs.bytes, LOG_BYTES);
+    }
+}
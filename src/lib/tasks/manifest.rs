@@ -0,0 +1,531 @@
+use super::types::{ByteSize, HealthProbe, Manifest, ManifestError, PortBinding, Task};
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.field.is_empty() {
+            write!(f, "{}: {}", self.source, self.message)
+        } else {
+            write!(f, "{}: {}: {}", self.source, self.field, self.message)
+        }
+    }
+}
+
+impl Error for ManifestError {}
+
+impl ManifestError {
+    fn new(source: &str, field: impl Into<String>, message: impl Into<String>) -> Self {
+        ManifestError {
+            source: source.to_string(),
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl ByteSize {
+    // * Number of bytes. Units are decimal (K, M, G, T) or binary (Ki, Mi,
+    // * Gi, Ti), with an optional trailing "B".
+    pub fn bytes(&self) -> Result<u64, String> {
+        let text = match self {
+            ByteSize::Bytes(bytes) => return Ok(*bytes),
+            ByteSize::Text(text) => text.trim(),
+        };
+
+        let split = text
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(text.len());
+        let (number, unit) = text.split_at(split);
+        let number: f64 = number
+            .parse()
+            .map_err(|_| format!("{:?} is not a size like 512Mi or 2G", text))?;
+        let multiplier: u64 = match unit.trim().trim_end_matches('B') {
+            "" => 1,
+            "K" | "k" => 1_000,
+            "M" => 1_000_000,
+            "G" => 1_000_000_000,
+            "T" => 1_000_000_000_000,
+            "Ki" => 1 << 10,
+            "Mi" => 1 << 20,
+            "Gi" => 1 << 30,
+            "Ti" => 1 << 40,
+            _ => return Err(format!("{:?} has an unknown unit {:?}", text, unit)),
+        };
+        Ok((number * multiplier as f64) as u64)
+    }
+}
+
+// * Task, service and label names use the container name alphabet, as names
+// * end up in container names
+pub fn valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphanumeric())
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+}
+
+impl Manifest {
+    // * Returns every problem with the manifest, each pointing at its field.
+    // * `source` names where the manifest came from in the errors.
+    pub fn validate(&self, source: &str) -> Vec<ManifestError> {
+        let mut errors = Vec::new();
+        let mut error = |field: String, message: String| {
+            errors.push(ManifestError::new(source, field, message));
+        };
+
+        if self.name.is_empty() {
+            error("name".into(), "is required".into());
+        } else if self.name.len() > 63 || !valid_name(&self.name) {
+            error(
+                "name".into(),
+                format!(
+                    "{:?} must be at most 63 letters, digits, '_', '.' or '-' and start with a letter or digit",
+                    self.name
+                ),
+            );
+        }
+
+        if self.image.trim().is_empty() && self.command.is_empty() {
+            error(
+                "image".into(),
+                "is required when there is no command".into(),
+            );
+        }
+
+        for key in self.env.keys() {
+            if key.is_empty() || key.contains('=') {
+                error(
+                    format!("env.{}", key),
+                    "keys must be non-empty and cannot contain '='".into(),
+                );
+            }
+        }
+
+        let cpu = self.resources.cpu;
+        if !cpu.is_finite() || cpu < 0.0 {
+            error(
+                "resources.cpu".into(),
+                format!("{} is not a non-negative number of CPUs", cpu),
+            );
+        }
+        if let Err(e) = self.resources.memory.bytes() {
+            error("resources.memory".into(), e);
+        }
+        if let Err(e) = self.resources.disk.bytes() {
+            error("resources.disk".into(), e);
+        }
+
+        let mut host_ports = HashSet::new();
+        for (i, port) in self.ports.iter().enumerate() {
+            if port.container == 0 {
+                error(format!("ports[{}].container", i), "cannot be 0".into());
+            }
+            match port.host {
+                Some(0) => error(
+                    format!("ports[{}].host", i),
+                    "cannot be 0, leave it out to let the runtime pick one".into(),
+                ),
+                Some(host) if !host_ports.insert((host, port.protocol)) => error(
+                    format!("ports[{}].host", i),
+                    format!("{}/{} is bound twice", host, port.protocol.as_str()),
+                ),
+                _ => {}
+            }
+        }
+
+        if let Some(check) = &self.health_check {
+            if check.interval == 0 {
+                error("health_check.interval".into(), "must be at least 1".into());
+            }
+            if check.timeout == 0 {
+                error("health_check.timeout".into(), "must be at least 1".into());
+            }
+            if check.failure_threshold == 0 {
+                error(
+                    "health_check.failure_threshold".into(),
+                    "must be at least 1".into(),
+                );
+            }
+            match &check.probe {
+                HealthProbe::Http { port: 0, .. } | HealthProbe::Tcp { port: 0 } => {
+                    error("health_check.port".into(), "cannot be 0".into());
+                }
+                HealthProbe::Http { path, .. } if !path.starts_with('/') => {
+                    error(
+                        "health_check.path".into(),
+                        format!("{:?} must start with '/'", path),
+                    );
+                }
+                HealthProbe::Exec { command } if command.is_empty() => {
+                    error("health_check.command".into(), "cannot be empty".into());
+                }
+                _ => {}
+            }
+        }
+
+        let placement = &self.placement;
+        for (field, selectors) in [
+            ("required", &placement.required),
+            ("forbidden", &placement.forbidden),
+            ("affinity", &placement.affinity),
+            ("anti_affinity", &placement.anti_affinity),
+        ] {
+            for (i, selector) in selectors.iter().enumerate() {
+                let key = selector
+                    .split_once('=')
+                    .map_or(selector.as_str(), |(key, _)| key);
+                if key.is_empty() || !valid_name(key) {
+                    error(
+                        format!("placement.{}[{}]", field, i),
+                        format!("{:?} is not a key=value or key selector", selector),
+                    );
+                }
+            }
+        }
+
+        for key in self.labels.keys() {
+            if key.len() > 63 || !valid_name(key) {
+                error(
+                    format!("labels.{}", key),
+                    "keys must be at most 63 letters, digits, '_', '.' or '-'".into(),
+                );
+            }
+        }
+
+        errors
+    }
+
+    // * The task the manifest describes. Expects a manifest without
+    // * validation errors; sizes that do not parse count as 0.
+    pub fn to_task(&self) -> Task {
+        Task {
+            name: self.name.clone(),
+            image: self.image.clone(),
+            command: self.command.clone(),
+            args: self.args.clone(),
+            env: self
+                .env
+                .iter()
+                .map(|(key, value)| format!("{}={}", key, value))
+                .collect(),
+            cpu: self.resources.cpu,
+            memory: self.resources.memory.bytes().unwrap_or_default(),
+            disk: self.resources.disk.bytes().unwrap_or_default(),
+            port_bindings: self
+                .ports
+                .iter()
+                .map(|port| PortBinding {
+                    container_port: port.container,
+                    protocol: port.protocol,
+                    host_ip: port.host_ip.clone(),
+                    host_port: port.host,
+                })
+                .collect(),
+            working_dir: self.working_dir.clone(),
+            user: self.user.clone(),
+            labels: self
+                .labels
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+            placement: self.placement.clone(),
+            restart_policy: self.restart.policy,
+            max_restarts: self.restart.max_restarts,
+            pull_policy: self.pull_policy,
+            health_check: self.health_check.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+// * Checks a set of manifests applied together: each one on its own, and
+// * that no two share a name. Manifests come with the source they were read
+// * from.
+pub fn validate_all<'a>(
+    manifests: impl IntoIterator<Item = (&'a str, &'a Manifest)>,
+) -> Vec<ManifestError> {
+    let mut errors = Vec::new();
+    let mut names: HashMap<&str, &str> = HashMap::new();
+
+    for (source, manifest) in manifests {
+        errors.extend(manifest.validate(source));
+        if manifest.name.is_empty() {
+            continue;
+        }
+        if let Some(first) = names.insert(&manifest.name, source) {
+            errors.push(ManifestError::new(
+                source,
+                "name",
+                format!("{:?} is already defined in {}", manifest.name, first),
+            ));
+        }
+    }
+    errors
+}
+
+// * Keeps the path serde was at when it failed, such as "ports[1].host_port",
+// * so the error points at the field like the validation errors do
+fn parse_error<E: fmt::Display>(
+    source: &str,
+    error: serde_path_to_error::Error<E>,
+) -> ManifestError {
+    let path = error.path().to_string();
+    let field = if path == "." { String::new() } else { path };
+    // serde_yaml already puts the path of nested errors in front
+    let message = error.into_inner().to_string();
+    let message = message
+        .strip_prefix(&format!("{}: ", field))
+        .map_or(message.clone(), str::to_string);
+    ManifestError::new(source, field, message)
+}
+
+// * Parses YAML holding one or more manifests separated by "---"
+pub fn from_yaml(source: &str, contents: &str) -> Result<Vec<Manifest>, ManifestError> {
+    serde_yaml::Deserializer::from_str(contents)
+        .map(|document| {
+            serde_path_to_error::deserialize(document).map_err(|e| parse_error(source, e))
+        })
+        .collect()
+}
+
+pub fn from_toml(source: &str, contents: &str) -> Result<Manifest, ManifestError> {
+    let document = toml::Deserializer::parse(contents)
+        .map_err(|e| ManifestError::new(source, "", e.to_string()))?;
+    serde_path_to_error::deserialize(document).map_err(|e| parse_error(source, e))
+}
+
+// * Reads the manifests in a .yaml, .yml or .toml file
+pub fn load(path: &Path) -> Result<Vec<Manifest>, ManifestError> {
+    let source = path.display().to_string();
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| ManifestError::new(&source, "", format!("unable to read: {}", e)))?;
+
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("yaml" | "yml") => from_yaml(&source, &contents),
+        Some("toml") => from_toml(&source, &contents).map(|manifest| vec![manifest]),
+        _ => Err(ManifestError::new(
+            &source,
+            "",
+            "manifests must be .yaml, .yml or .toml files",
+        )),
+    }
+}
+
+fn is_manifest(path: &Path) -> bool {
+    path.is_file()
+        && matches!(
+            path.extension().and_then(|extension| extension.to_str()),
+            Some("yaml" | "yml" | "toml")
+        )
+}
+
+// * Reads and validates the manifest file at `path`, or every manifest file
+// * directly inside the directory at `path` in name order. Returns every
+// * error found across the files instead of the manifests.
+pub fn load_all(path: &Path) -> Result<Vec<Manifest>, Vec<ManifestError>> {
+    let source = path.display().to_string();
+    let files: Vec<PathBuf> = if path.is_dir() {
+        let entries = std::fs::read_dir(path).map_err(|e| {
+            vec![ManifestError::new(
+                &source,
+                "",
+                format!("unable to read: {}", e),
+            )]
+        })?;
+        let mut files: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| is_manifest(path))
+            .collect();
+        files.sort();
+        files
+    } else {
+        vec![path.to_path_buf()]
+    };
+
+    let mut loaded = Vec::new();
+    let mut errors = Vec::new();
+    for file in files {
+        match load(&file) {
+            Ok(manifests) => loaded.extend(
+                manifests
+                    .into_iter()
+                    .map(|manifest| (file.display().to_string(), manifest)),
+            ),
+            Err(e) => errors.push(e),
+        }
+    }
+    if loaded.is_empty() && errors.is_empty() {
+        errors.push(ManifestError::new(&source, "", "no manifests found"));
+    }
+
+    errors.extend(validate_all(
+        loaded
+            .iter()
+            .map(|(source, manifest)| (source.as_str(), manifest)),
+    ));
+    if errors.is_empty() {
+        Ok(loaded.into_iter().map(|(_, manifest)| manifest).collect())
+    } else {
+        Err(errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(yaml: &str) -> Manifest {
+        from_yaml("test.yaml", yaml).unwrap().remove(0)
+    }
+
+    fn fields(errors: &[ManifestError]) -> Vec<&str> {
+        errors.iter().map(|error| error.field.as_str()).collect()
+    }
+
+    #[test]
+    fn byte_sizes_parse_decimal_and_binary_units() {
+        let bytes = |text: &str| ByteSize::Text(text.to_string()).bytes();
+
+        assert_eq!(ByteSize::Bytes(42).bytes(), Ok(42));
+        assert_eq!(bytes("512"), Ok(512));
+        assert_eq!(bytes("2K"), Ok(2_000));
+        assert_eq!(bytes("2G"), Ok(2_000_000_000));
+        assert_eq!(bytes("512Mi"), Ok(512 << 20));
+        assert_eq!(bytes("1.5Gi"), Ok(3 << 29));
+        assert_eq!(bytes("1GiB"), Ok(1 << 30));
+        assert_eq!(bytes(" 10MB "), Ok(10_000_000));
+        assert!(bytes("10 parsecs").is_err());
+        assert!(bytes("Mi").is_err());
+    }
+
+    #[test]
+    fn a_valid_manifest_has_no_errors() {
+        let manifest = manifest(
+            r#"
+name: web
+image: nginx:1.27
+env: { MODE: prod }
+resources: { cpu: 0.5, memory: 256Mi }
+ports: [{ container: 80, host: 8080 }]
+health_check: { type: http, port: 80, path: /healthz }
+placement: { required: [arch=x86_64], anti_affinity: [app] }
+labels: { app: web }
+"#,
+        );
+
+        assert!(manifest.validate("test.yaml").is_empty());
+    }
+
+    #[test]
+    fn every_error_points_at_its_field() {
+        let manifest = manifest(
+            r#"
+name: -web
+env: { "A=B": x }
+resources: { cpu: -1, memory: lots, disk: 1X }
+ports:
+  - { container: 0 }
+  - { container: 80, host: 0 }
+  - { container: 81, host: 8080 }
+  - { container: 82, host: 8080 }
+health_check: { type: http, port: 0, interval: 0, timeout: 0, failure_threshold: 0 }
+placement: { required: ["=x"], forbidden: [ok], affinity: ["bad key"], anti_affinity: [""] }
+labels: { "bad label": x }
+"#,
+        );
+
+        let errors = manifest.validate("test.yaml");
+        assert_eq!(
+            fields(&errors),
+            [
+                "name",
+                "image",
+                "env.A=B",
+                "resources.cpu",
+                "resources.memory",
+                "resources.disk",
+                "ports[0].container",
+                "ports[1].host",
+                "ports[3].host",
+                "health_check.interval",
+                "health_check.timeout",
+                "health_check.failure_threshold",
+                "health_check.port",
+                "placement.required[0]",
+                "placement.affinity[0]",
+                "placement.anti_affinity[0]",
+                "labels.bad label",
+            ]
+        );
+        assert_eq!(
+            errors[8].to_string(),
+            "test.yaml: ports[3].host: 8080/tcp is bound twice"
+        );
+    }
+
+    #[test]
+    fn probe_errors_point_at_their_field() {
+        let path =
+            manifest("name: a\nimage: x\nhealth_check: { type: http, port: 80, path: healthz }");
+        assert_eq!(fields(&path.validate("a")), ["health_check.path"]);
+
+        let command = manifest("name: a\nimage: x\nhealth_check: { type: exec, command: [] }");
+        assert_eq!(fields(&command.validate("a")), ["health_check.command"]);
+    }
+
+    #[test]
+    fn a_command_stands_in_for_the_image() {
+        let manifest = manifest("name: job\ncommand: [echo, hi]");
+        assert!(manifest.validate("a").is_empty());
+    }
+
+    #[test]
+    fn duplicate_names_across_sources_are_reported_once_each() {
+        let web = manifest("name: web\nimage: nginx");
+        let other = manifest("name: other\nimage: nginx");
+
+        let errors = validate_all([("a.yaml", &web), ("b.yaml", &other), ("c.yaml", &web)]);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            r#"c.yaml: name: "web" is already defined in a.yaml"#
+        );
+    }
+
+    #[test]
+    fn yaml_documents_are_split_and_toml_is_single() {
+        let manifests = from_yaml("a.yaml", "name: a\nimage: x\n---\nname: b\nimage: y\n").unwrap();
+        assert_eq!(manifests.len(), 2);
+        assert_eq!(manifests[1].name, "b");
+
+        let manifest = from_toml("a.toml", "name = \"c\"\nimage = \"z\"\n").unwrap();
+        assert_eq!(manifest.name, "c");
+
+        // Parse errors point at their field like the validation errors do
+        let error = from_yaml("a.yaml", "name: a\nimage: x\nimgae: y\n").unwrap_err();
+        assert_eq!(error.field, "imgae");
+        assert!(
+            error
+                .to_string()
+                .starts_with("a.yaml: imgae: unknown field")
+        );
+
+        let error = from_yaml(
+            "a.yaml",
+            "name: a\nimage: x\nports:\n  - container: 80\n  - container: http\n",
+        )
+        .unwrap_err();
+        assert_eq!(error.field, "ports[1].container");
+        assert!(error.message.starts_with("invalid type"));
+
+        let error = from_toml("a.toml", "name = \"a\"\n[resources]\nmemroy = 1\n").unwrap_err();
+        assert_eq!(error.field, "resources.memroy");
+
+        let error = from_toml("a.toml", "name = \"a\"\n[resources]\nmemory = [1]\n").unwrap_err();
+        assert_eq!(error.field, "resources.memory");
+    }
+}
@@ -0,0 +1,190 @@
+use std::{
+    sync::atomic::{AtomicU16, Ordering},
+    time::SystemTime,
+};
+
+use error_stack::Report;
+
+use super::runtime::Runtime;
+use super::types::{
+    Config, ContainerInspect, ContainerStatus, DockerError, ExecSession, ImageInfo, LogOptions,
+    LogStream, MemoryContainer, MemoryRuntime, PortBinding, PullEvent, PullStream, RuntimeResult,
+};
+
+impl MemoryRuntime {
+    pub fn new() -> Self {
+        MemoryRuntime {
+            next_port: AtomicU16::new(32768),
+            ..Default::default()
+        }
+    }
+
+    fn not_found(container_id: &str) -> Report<DockerError> {
+        Report::new(DockerError::ClientError(format!(
+            "No such container: {}",
+            container_id
+        )))
+    }
+}
+
+impl Runtime for MemoryRuntime {
+    async fn pull(&self, config: &Config) -> RuntimeResult<PullStream> {
+        println!("Pulling image: {}", config.image);
+        self.images.lock().unwrap().insert(config.image.clone());
+        let event = PullEvent {
+            timestamp: SystemTime::now(),
+            status: format!("Pulled {}", config.image),
+            layer: None,
+            current: None,
+            total: None,
+        };
+        Ok(Box::pin(futures_util::stream::iter([Ok(event)])))
+    }
+
+    async fn has_image(&self, image: &str) -> RuntimeResult<bool> {
+        Ok(self.images.lock().unwrap().contains(image))
+    }
+
+    // * Images take no space, since nothing is ever downloaded
+    async fn images(&self) -> RuntimeResult<Vec<ImageInfo>> {
+        Ok(self
+            .images
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|image| ImageInfo {
+                id: image.clone(),
+                tags: vec![image.clone()],
+                size: 0,
+                created: 0,
+            })
+            .collect())
+    }
+
+    async fn create(&self, config: &Config) -> RuntimeResult<String> {
+        let container_id = uuid::Uuid::new_v4().simple().to_string();
+        let ports = config
+            .port_bindings
+            .iter()
+            .map(|binding| PortBinding {
+                host_port: binding
+                    .host_port
+                    .or_else(|| Some(self.next_port.fetch_add(1, Ordering::Relaxed))),
+                ..binding.clone()
+            })
+            .collect();
+        self.containers.lock().unwrap().insert(
+            container_id.clone(),
+            MemoryContainer {
+                status: ContainerStatus::Created,
+                exit_code: None,
+                ports,
+            },
+        );
+        println!("Container created successfully: {}", container_id);
+        Ok(container_id)
+    }
+
+    async fn start(&self, container_id: &str) -> RuntimeResult<()> {
+        println!("Starting container: {}", container_id);
+        let mut containers = self.containers.lock().unwrap();
+        let container = containers
+            .get_mut(container_id)
+            .ok_or_else(|| Self::not_found(container_id))?;
+        container.status = ContainerStatus::Running;
+        container.exit_code = None;
+        Ok(())
+    }
+
+    async fn stop(&self, container_id: &str) -> RuntimeResult<()> {
+        println!("Stopping container: {}", container_id);
+        let mut containers = self.containers.lock().unwrap();
+        let container = containers
+            .get_mut(container_id)
+            .ok_or_else(|| Self::not_found(container_id))?;
+        if container.status == ContainerStatus::Running {
+            container.status = ContainerStatus::Exited;
+            container.exit_code = Some(0);
+        }
+        Ok(())
+    }
+
+    async fn inspect(&self, container_id: &str) -> RuntimeResult<ContainerInspect> {
+        let containers = self.containers.lock().unwrap();
+        let container = containers
+            .get(container_id)
+            .ok_or_else(|| Self::not_found(container_id))?;
+        Ok(ContainerInspect {
+            status: container.status.clone(),
+            exit_code: container.exit_code,
+            ports: container.ports.clone(),
+        })
+    }
+
+    async fn remove(&self, container_id: &str) -> RuntimeResult<()> {
+        println!("Removing container: {}", container_id);
+        self.containers
+            .lock()
+            .unwrap()
+            .remove(container_id)
+            .map(|_| ())
+            .ok_or_else(|| Self::not_found(container_id))
+    }
+
+    // * Nothing runs, so there is never anything to read
+    async fn logs(&self, container_id: &str, _options: &LogOptions) -> RuntimeResult<LogStream> {
+        if !self.containers.lock().unwrap().contains_key(container_id) {
+            return Err(Self::not_found(container_id));
+        }
+        Ok(Box::pin(futures_util::stream::empty()))
+    }
+
+    // * Signals that end a process make the container exit with 128 + signal
+    // * number, like a shell reports it; others are ignored
+    async fn kill(&self, container_id: &str, signal: &str) -> RuntimeResult<()> {
+        println!("Sending {} to container: {}", signal, container_id);
+        let exit_code = match signal.trim_start_matches("SIG") {
+            "INT" | "2" => Some(130),
+            "KILL" | "9" => Some(137),
+            "TERM" | "15" => Some(143),
+            _ => None,
+        };
+
+        let mut containers = self.containers.lock().unwrap();
+        let container = containers
+            .get_mut(container_id)
+            .ok_or_else(|| Self::not_found(container_id))?;
+        if let Some(exit_code) = exit_code
+            && container.status == ContainerStatus::Running
+        {
+            container.status = ContainerStatus::Exited;
+            container.exit_code = Some(exit_code);
+        }
+        Ok(())
+    }
+
+    // * Commands never run, so they succeed as long as the container is running
+    async fn exec_session(
+        &self,
+        container_id: &str,
+        command: &[String],
+        _stdin: bool,
+    ) -> RuntimeResult<ExecSession> {
+        println!("Running {:?} in container: {}", command, container_id);
+        let containers = self.containers.lock().unwrap();
+        let container = containers
+            .get(container_id)
+            .ok_or_else(|| Self::not_found(container_id))?;
+        match container.status {
+            ContainerStatus::Running => Ok(ExecSession {
+                input: Box::pin(tokio::io::sink()),
+                output: Box::pin(futures_util::stream::empty()),
+                exit: Box::pin(std::future::ready(Ok(0))),
+            }),
+            _ => Err(Report::new(DockerError::ContainerExecError(format!(
+                "Container {} is not running",
+                container_id
+            )))),
+        }
+    }
+}
@@ -1,3 +1,11 @@
-pub mod types;
 pub mod docker;
-pub mod state;
\ No newline at end of file
+pub mod events;
+pub mod health;
+pub mod logs;
+pub mod manifest;
+pub mod memory;
+pub mod ports;
+pub mod process;
+pub mod runtime;
+pub mod state;
+pub mod types;
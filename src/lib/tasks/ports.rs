@@ -0,0 +1,119 @@
+use super::types::{PortBinding, Protocol};
+
+impl Protocol {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Protocol::Tcp => "tcp",
+            Protocol::Udp => "udp",
+            Protocol::Sctp => "sctp",
+        }
+    }
+}
+
+impl PortBinding {
+    // * Docker style key for the container side of the binding, e.g. "80/tcp"
+    pub fn port_key(&self) -> String {
+        format!("{}/{}", self.container_port, self.protocol.as_str())
+    }
+
+    // * Parses a Docker style "80/tcp" key; the host side is left empty
+    pub fn from_port_key(key: &str) -> Option<Self> {
+        let (port, protocol) = key.split_once('/').unwrap_or((key, "tcp"));
+        let protocol = match protocol {
+            "tcp" => Protocol::Tcp,
+            "udp" => Protocol::Udp,
+            "sctp" => Protocol::Sctp,
+            _ => return None,
+        };
+
+        Some(PortBinding {
+            container_port: port.parse().ok()?,
+            protocol,
+            host_ip: None,
+            host_port: None,
+        })
+    }
+
+    fn binds_all_interfaces(&self) -> bool {
+        matches!(
+            self.host_ip.as_deref(),
+            None | Some("") | Some("0.0.0.0") | Some("::")
+        )
+    }
+
+    // * Two bindings conflict when they claim the same host port and protocol
+    // * on overlapping interfaces. Bindings without a host port never conflict
+    // * because the runtime picks a free one.
+    pub fn conflicts_with(&self, other: &PortBinding) -> bool {
+        match (self.host_port, other.host_port) {
+            (Some(a), Some(b)) if a == b && self.protocol == other.protocol => {
+                self.binds_all_interfaces()
+                    || other.binds_all_interfaces()
+                    || self.host_ip == other.host_ip
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binding(host_ip: Option<&str>, host_port: Option<u16>, protocol: Protocol) -> PortBinding {
+        PortBinding {
+            container_port: 80,
+            protocol,
+            host_ip: host_ip.map(str::to_string),
+            host_port,
+        }
+    }
+
+    #[test]
+    fn conflicts_need_the_same_host_port_protocol_and_an_overlapping_interface() {
+        let tcp = |ip, port| binding(ip, port, Protocol::Tcp);
+
+        for (a, b, expected) in [
+            (tcp(None, Some(8080)), tcp(None, Some(8080)), true),
+            (tcp(None, Some(8080)), tcp(None, Some(8081)), false),
+            // The same port on another protocol is a different socket
+            (
+                tcp(None, Some(53)),
+                binding(None, Some(53), Protocol::Udp),
+                false,
+            ),
+            // All interfaces overlap any specific one
+            (
+                tcp(Some("0.0.0.0"), Some(8080)),
+                tcp(Some("10.0.0.1"), Some(8080)),
+                true,
+            ),
+            (
+                tcp(Some("::"), Some(8080)),
+                tcp(Some("10.0.0.1"), Some(8080)),
+                true,
+            ),
+            (
+                tcp(Some(""), Some(8080)),
+                tcp(Some("10.0.0.1"), Some(8080)),
+                true,
+            ),
+            (
+                tcp(Some("10.0.0.1"), Some(8080)),
+                tcp(Some("10.0.0.2"), Some(8080)),
+                false,
+            ),
+            (
+                tcp(Some("10.0.0.1"), Some(8080)),
+                tcp(Some("10.0.0.1"), Some(8080)),
+                true,
+            ),
+            // The runtime picks a free port when none is asked for
+            (tcp(None, None), tcp(None, None), false),
+            (tcp(None, None), tcp(None, Some(8080)), false),
+        ] {
+            assert_eq!(a.conflicts_with(&b), expected, "{:?} and {:?}", a, b);
+            assert_eq!(b.conflicts_with(&a), expected, "{:?} and {:?}", b, a);
+        }
+    }
+}
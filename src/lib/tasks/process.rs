@@ -0,0 +1,335 @@
+use std::{pin::Pin, process::Stdio, sync::Arc};
+
+use error_stack::Report;
+use futures_util::stream;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite},
+    process::Command,
+    sync::mpsc,
+};
+
+use super::logs::{collect_output, read_logs};
+use super::runtime::Runtime;
+use super::types::{
+    Config, ContainerInspect, ContainerStatus, DockerError, ExecSession, ImageInfo, LogOptions,
+    LogStream, OutputStream, PortBinding, ProcessContainer, ProcessLogs, ProcessRuntime,
+    PullStream, RuntimeResult,
+};
+
+impl ProcessRuntime {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn not_found(container_id: &str) -> Report<DockerError> {
+        Report::new(DockerError::ClientError(format!(
+            "No such process: {}",
+            container_id
+        )))
+    }
+}
+
+impl Runtime for ProcessRuntime {
+    // * Processes run local programs, so there are no images to pull
+    async fn pull(&self, _config: &Config) -> RuntimeResult<PullStream> {
+        Ok(Box::pin(stream::empty()))
+    }
+
+    async fn has_image(&self, _image: &str) -> RuntimeResult<bool> {
+        Ok(true)
+    }
+
+    async fn images(&self) -> RuntimeResult<Vec<ImageInfo>> {
+        Ok(Vec::new())
+    }
+
+    async fn create(&self, config: &Config) -> RuntimeResult<String> {
+        if config.entrypoint.is_empty() && config.cmd.is_empty() {
+            return Err(Report::new(DockerError::ContainerCreationError(format!(
+                "Task {} has no command to run as a process",
+                config.name
+            ))));
+        }
+
+        let container_id = uuid::Uuid::new_v4().simple().to_string();
+        self.processes.lock().await.insert(
+            container_id.clone(),
+            ProcessContainer {
+                config: config.clone(),
+                child: None,
+                exit_code: None,
+                logs: Arc::new(std::sync::Mutex::new(ProcessLogs::new(0))),
+            },
+        );
+        Ok(container_id)
+    }
+
+    async fn start(&self, container_id: &str) -> RuntimeResult<()> {
+        let mut processes = self.processes.lock().await;
+        let process = processes
+            .get_mut(container_id)
+            .ok_or_else(|| Self::not_found(container_id))?;
+
+        // Like a container, the entrypoint runs with the cmd as its arguments
+        let argv: Vec<&String> = process
+            .config
+            .entrypoint
+            .iter()
+            .chain(process.config.cmd.iter())
+            .collect();
+
+        let mut command = Command::new(argv[0]);
+        command
+            .args(&argv[1..])
+            .envs(
+                process
+                    .config
+                    .env
+                    .iter()
+                    .filter_map(|var| var.split_once('=')),
+            )
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        if !process.config.working_dir.is_empty() {
+            command.current_dir(&process.config.working_dir);
+        }
+
+        let mut child = command.spawn().map_err(|e| {
+            Report::new(DockerError::ContainerStartError(format!(
+                "Failed to spawn {:?}: {}",
+                argv, e
+            )))
+        })?;
+        println!("Started process {:?} for {}", child.id(), container_id);
+
+        *process.logs.lock().unwrap() = ProcessLogs::new(2);
+        if let Some(stdout) = child.stdout.take() {
+            tokio::spawn(collect_output(
+                process.logs.clone(),
+                OutputStream::Stdout,
+                stdout,
+            ));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            tokio::spawn(collect_output(
+                process.logs.clone(),
+                OutputStream::Stderr,
+                stderr,
+            ));
+        }
+
+        process.child = Some(child);
+        process.exit_code = None;
+        Ok(())
+    }
+
+    async fn stop(&self, container_id: &str) -> RuntimeResult<()> {
+        let mut processes = self.processes.lock().await;
+        let process = processes
+            .get_mut(container_id)
+            .ok_or_else(|| Self::not_found(container_id))?;
+
+        if let Some(child) = process.child.as_mut()
+            && child.try_wait().ok().flatten().is_none()
+        {
+            child.kill().await.map_err(|e| {
+                Report::new(DockerError::ContainerStopError(format!(
+                    "Failed to kill process for {}: {}",
+                    container_id, e
+                )))
+            })?;
+        }
+        Ok(())
+    }
+
+    async fn inspect(&self, container_id: &str) -> RuntimeResult<ContainerInspect> {
+        let mut processes = self.processes.lock().await;
+        let process = processes
+            .get_mut(container_id)
+            .ok_or_else(|| Self::not_found(container_id))?;
+
+        let status = match process.child.as_mut() {
+            None => ContainerStatus::Created,
+            Some(child) => match child.try_wait() {
+                Ok(Some(exit)) => {
+                    // Processes killed by a signal have no exit code
+                    process.exit_code = Some(exit.code().unwrap_or(-1) as i64);
+                    ContainerStatus::Exited
+                }
+                Ok(None) => ContainerStatus::Running,
+                Err(_) => ContainerStatus::Unknown,
+            },
+        };
+
+        // A process binds its ports itself, on the port it was asked for
+        let ports = process
+            .config
+            .port_bindings
+            .iter()
+            .map(|binding| PortBinding {
+                host_port: binding.host_port.or(Some(binding.container_port)),
+                ..binding.clone()
+            })
+            .collect();
+
+        Ok(ContainerInspect {
+            status,
+            exit_code: process.exit_code,
+            ports,
+        })
+    }
+
+    async fn remove(&self, container_id: &str) -> RuntimeResult<()> {
+        let process = self
+            .processes
+            .lock()
+            .await
+            .remove(container_id)
+            .ok_or_else(|| Self::not_found(container_id))?;
+
+        if let Some(mut child) = process.child {
+            let _ = child.kill().await;
+        }
+        Ok(())
+    }
+
+    async fn logs(&self, container_id: &str, options: &LogOptions) -> RuntimeResult<LogStream> {
+        let logs = self
+            .processes
+            .lock()
+            .await
+            .get(container_id)
+            .map(|process| process.logs.clone())
+            .ok_or_else(|| Self::not_found(container_id))?;
+        Ok(read_logs(&logs, options))
+    }
+
+    // * Delivers the signal with kill(1), since the child handle can only send
+    // * SIGKILL itself
+    async fn kill(&self, container_id: &str, signal: &str) -> RuntimeResult<()> {
+        let pid = self
+            .processes
+            .lock()
+            .await
+            .get(container_id)
+            .ok_or_else(|| Self::not_found(container_id))?
+            .child
+            .as_ref()
+            .and_then(|child| child.id())
+            .ok_or_else(|| {
+                Report::new(DockerError::ContainerKillError(format!(
+                    "Process for {} is not running",
+                    container_id
+                )))
+            })?;
+
+        let status = Command::new("kill")
+            .args(["-s", signal.trim_start_matches("SIG"), &pid.to_string()])
+            .status()
+            .await
+            .map_err(|e| {
+                Report::new(DockerError::ContainerKillError(format!(
+                    "Failed to run kill for {}: {}",
+                    container_id, e
+                )))
+            })?;
+        if !status.success() {
+            return Err(Report::new(DockerError::ContainerKillError(format!(
+                "kill -s {} {} exited with {}",
+                signal, pid, status
+            ))));
+        }
+        Ok(())
+    }
+
+    // * Runs the command as another local process with the task's environment
+    // * and working directory
+    async fn exec_session(
+        &self,
+        container_id: &str,
+        command: &[String],
+        stdin: bool,
+    ) -> RuntimeResult<ExecSession> {
+        let config = self
+            .processes
+            .lock()
+            .await
+            .get(container_id)
+            .map(|process| process.config.clone())
+            .ok_or_else(|| Self::not_found(container_id))?;
+
+        let Some((program, args)) = command.split_first() else {
+            return Err(Report::new(DockerError::ContainerExecError(
+                "Empty command".to_string(),
+            )));
+        };
+
+        let mut exec = Command::new(program);
+        exec.args(args)
+            .envs(config.env.iter().filter_map(|var| var.split_once('=')))
+            .stdin(if stdin { Stdio::piped() } else { Stdio::null() })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        if !config.working_dir.is_empty() {
+            exec.current_dir(&config.working_dir);
+        }
+
+        let mut child = exec.spawn().map_err(|e| {
+            Report::new(DockerError::ContainerExecError(format!(
+                "Failed to run {:?} for {}: {}",
+                command, container_id, e
+            )))
+        })?;
+
+        let input: Pin<Box<dyn AsyncWrite + Send>> = match child.stdin.take() {
+            Some(stdin) => Box::pin(stdin),
+            None => Box::pin(tokio::io::sink()),
+        };
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        if let Some(stdout) = child.stdout.take() {
+            tokio::spawn(forward_output(OutputStream::Stdout, stdout, sender.clone()));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            tokio::spawn(forward_output(OutputStream::Stderr, stderr, sender));
+        }
+        let output = stream::unfold(receiver, |mut receiver| async move {
+            let chunk = receiver.recv().await?;
+            Some((Ok(chunk), receiver))
+        });
+
+        let command = command.to_vec();
+        let exit = async move {
+            let status = child.wait().await.map_err(|e| {
+                Report::new(DockerError::ContainerExecError(format!(
+                    "Failed to wait for {:?}: {}",
+                    command, e
+                )))
+            })?;
+            Ok(status.code().unwrap_or(-1) as i64)
+        };
+
+        Ok(ExecSession {
+            input,
+            output: Box::pin(output),
+            exit: Box::pin(exit),
+        })
+    }
+}
+
+// * Passes what an exec'd process prints on to its session as it arrives
+async fn forward_output<T: AsyncRead + Unpin>(
+    stream: OutputStream,
+    mut output: T,
+    sender: mpsc::UnboundedSender<(OutputStream, Vec<u8>)>,
+) {
+    let mut buffer = vec![0; 4096];
+    while let Ok(read) = output.read(&mut buffer).await {
+        if read == 0 || sender.send((stream, buffer[..read].to_vec())).is_err() {
+            break;
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use std::future::Future;
+
+use error_stack::Report;
+use futures_util::StreamExt;
+
+use super::types::{
+    Config, ContainerInspect, DockerError, DockerResponse, DockerResult, ExecOutput, ExecSession,
+    ImageInfo, LogOptions, LogStream, OutputStream, PullEvent, PullPolicy, PullStream,
+    RuntimeResult,
+};
+
+// * Runtime is everything a worker needs from a container engine. Futures are
+// * required to be Send so workers can drive them from spawned tokio tasks.
+pub trait Runtime: Send + Sync + 'static {
+    // * Pulls the config's image, streaming the registry's progress
+    fn pull(&self, config: &Config) -> impl Future<Output = RuntimeResult<PullStream>> + Send;
+
+    // * Whether the image is in the runtime's local cache
+    fn has_image(&self, image: &str) -> impl Future<Output = RuntimeResult<bool>> + Send;
+
+    // * Every image in the runtime's local cache
+    fn images(&self) -> impl Future<Output = RuntimeResult<Vec<ImageInfo>>> + Send;
+
+    // * Creates the container and returns its id without starting it
+    fn create(&self, config: &Config) -> impl Future<Output = RuntimeResult<String>> + Send;
+
+    fn start(&self, container_id: &str) -> impl Future<Output = RuntimeResult<()>> + Send;
+
+    fn stop(&self, container_id: &str) -> impl Future<Output = RuntimeResult<()>> + Send;
+
+    fn inspect(
+        &self,
+        container_id: &str,
+    ) -> impl Future<Output = RuntimeResult<ContainerInspect>> + Send;
+
+    fn remove(&self, container_id: &str) -> impl Future<Output = RuntimeResult<()>> + Send;
+
+    // * Streams what the container printed. With `follow` the stream only ends
+    // * once the container has exited.
+    fn logs(
+        &self,
+        container_id: &str,
+        options: &LogOptions,
+    ) -> impl Future<Output = RuntimeResult<LogStream>> + Send;
+
+    // * Sends a signal such as "SIGHUP" to the container's main process
+    fn kill(
+        &self,
+        container_id: &str,
+        signal: &str,
+    ) -> impl Future<Output = RuntimeResult<()>> + Send;
+
+    // * Starts a command inside a running container. Without `stdin` the
+    // * command's stdin is closed from the start.
+    fn exec_session(
+        &self,
+        container_id: &str,
+        command: &[String],
+        stdin: bool,
+    ) -> impl Future<Output = RuntimeResult<ExecSession>> + Send;
+
+    // * Runs a command inside a running container and collects its output
+    fn exec(
+        &self,
+        container_id: &str,
+        command: &[String],
+    ) -> impl Future<Output = RuntimeResult<ExecOutput>> + Send {
+        async move {
+            let session = self.exec_session(container_id, command, false).await?;
+            let mut output = session.output;
+            let mut stdout = Vec::new();
+            let mut stderr = Vec::new();
+            while let Some(chunk) = output.next().await {
+                match chunk? {
+                    (OutputStream::Stdout, data) => stdout.extend(data),
+                    (OutputStream::Stderr, data) => stderr.extend(data),
+                }
+            }
+
+            Ok(ExecOutput {
+                exit_code: session.exit.await?,
+                stdout: String::from_utf8_lossy(&stdout).into_owned(),
+                stderr: String::from_utf8_lossy(&stderr).into_owned(),
+            })
+        }
+    }
+
+    // * Makes the config's image available as its pull policy asks, handing
+    // * every pull event to `on_progress`
+    fn prepare_image(
+        &self,
+        config: &Config,
+        mut on_progress: impl FnMut(PullEvent) + Send,
+    ) -> impl Future<Output = RuntimeResult<()>> + Send {
+        async move {
+            let cached = match config.pull_policy {
+                PullPolicy::Always => false,
+                PullPolicy::IfNotPresent | PullPolicy::Never => {
+                    self.has_image(&config.image).await?
+                }
+            };
+            if cached {
+                println!("Using cached image: {}", config.image);
+                return Ok(());
+            }
+            if config.pull_policy == PullPolicy::Never {
+                return Err(Report::new(DockerError::ImageNotFoundError(format!(
+                    "{} is not cached and the pull policy is never",
+                    config.image
+                ))));
+            }
+
+            let mut events = self.pull(config).await?;
+            while let Some(event) = events.next().await {
+                on_progress(event?);
+            }
+            Ok(())
+        }
+    }
+
+    // * Prepares the image, then creates and starts a container for the config
+    fn run(
+        &self,
+        config: &Config,
+        on_progress: impl FnMut(PullEvent) + Send,
+    ) -> impl Future<Output = DockerResult> + Send {
+        async move {
+            self.prepare_image(config, on_progress).await?;
+            let container_id = self.create(config).await?;
+            if let Err(e) = self.start(&container_id).await {
+                // The task never learns the id, so nothing else would remove
+                // the container, and its name would block every restart
+                if let Err(remove_error) = self.remove(&container_id).await {
+                    eprintln!(
+                        "Error removing container {} that failed to start: {:?}",
+                        container_id, remove_error
+                    );
+                }
+                return Err(e);
+            }
+
+            println!("Container {} started successfully.", config.name);
+            Ok(DockerResponse {
+                action: Some("Start".to_string()),
+                container_id: Some(container_id),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lib::tasks::types::ProcessRuntime;
+
+    #[tokio::test]
+    async fn a_container_that_fails_to_start_is_removed() {
+        let runtime = ProcessRuntime::new();
+        let config = Config {
+            name: "task-missing".to_string(),
+            entrypoint: vec!["/nonexistent/r_cube-test-binary".to_string()],
+            ..Default::default()
+        };
+
+        assert!(runtime.run(&config, |_| {}).await.is_err());
+        assert!(runtime.processes.lock().await.is_empty());
+    }
+}
@@ -1,27 +1,193 @@
-use std::collections::HashMap;
+use std::time::SystemTime;
 
-use super::types::State;
+use super::types::{RestartPolicy, State, Task, Transition};
 
 pub fn valid_state_transition(src: &State, dst: &State) -> bool {
-    let state_transition_map: HashMap<State, Vec<State>> = {
-        let mut map = HashMap::new();
-        map.insert(State::Pending, vec![State::Scheduled]);
-        map.insert(
-            State::Scheduled,
-            vec![State::Scheduled, State::Running, State::Failed],
-        );
-        map.insert(
-            State::Running,
-            vec![State::Running, State::Completed, State::Failed],
+    src.next_states().contains(dst)
+}
+
+impl State {
+    // * States a task may move to from this one
+    pub fn next_states(&self) -> &'static [State] {
+        match self {
+            // A task waiting for a worker records why it still waits
+            State::Pending => &[State::Pending, State::Scheduled, State::Cancelled],
+            State::Scheduled => &[
+                State::Scheduled,
+                State::Running,
+                State::Stopping,
+                State::Failed,
+                State::Cancelled,
+                State::Unknown,
+            ],
+            State::Running => &[
+                State::Running,
+                State::Stopping,
+                State::Restarting,
+                State::Completed,
+                State::Failed,
+                State::Unknown,
+            ],
+            // A stop the runtime failed leaves the container running
+            State::Stopping => &[
+                State::Stopping,
+                State::Running,
+                State::Completed,
+                State::Failed,
+                State::Cancelled,
+                State::Unknown,
+            ],
+            State::Restarting => &[
+                State::Restarting,
+                State::Scheduled,
+                State::Running,
+                State::Stopping,
+                State::Cancelled,
+                State::Failed,
+            ],
+            // Finished tasks only run again when the manager restarts them. A
+            // completed task whose container was stopped for failing its
+            // health check counts as failed.
+            State::Completed => &[State::Restarting, State::Scheduled, State::Failed],
+            State::Failed => &[State::Restarting, State::Scheduled],
+            State::Cancelled => &[],
+            State::Unknown => &[
+                State::Running,
+                State::Stopping,
+                State::Restarting,
+                State::Completed,
+                State::Failed,
+            ],
+        }
+    }
+
+    // * Terminal states no longer hold resources on a worker
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, State::Completed | State::Failed | State::Cancelled)
+    }
+}
+
+impl Task {
+    // * Moves the task to `to` and records the change in its history. A move
+    // * the state table does not allow is logged and leaves the task as is.
+    // * Returns whether the task moved.
+    pub fn transition(&mut self, to: State, reason: impl Into<String>) -> bool {
+        let reason = reason.into();
+        if !valid_state_transition(&self.state, &to) {
+            eprintln!(
+                "Task {} cannot move from {:?} to {:?} ({}), keeping it {:?}",
+                self.id, self.state, to, reason, self.state
+            );
+            return false;
+        }
+
+        self.history.push(Transition {
+            timestamp: SystemTime::now(),
+            from: self.state.clone(),
+            to: to.clone(),
+            reason,
+        });
+        self.state = to;
+        true
+    }
+
+    // * Adds the transitions another copy of the task recorded that this one
+    // * is missing, in their order. The copies were recorded on different
+    // * clocks, so their timestamps do not say which came first.
+    pub fn merge_history(&mut self, other: &[Transition]) {
+        for transition in other {
+            if !self.history.contains(transition) {
+                self.history.push(transition.clone());
+            }
+        }
+    }
+
+    // * Records that the manager changed the task, which makes what workers
+    // * reported before stale
+    pub fn next_generation(&mut self) {
+        self.generation += 1;
+    }
+
+    // * When the task last changed state, if it ever did
+    pub fn last_transition(&self) -> Option<SystemTime> {
+        self.history.last().map(|transition| transition.timestamp)
+    }
+
+    // * Records that the task's container exited, with its exit code if known
+    pub fn exited(&mut self, exit_code: Option<i64>, reason: impl Into<String>) {
+        let state = match exit_code {
+            Some(0) => State::Completed,
+            _ => State::Failed,
+        };
+        if self.transition(state, reason) {
+            self.exit_code = exit_code;
+            self.finish_time = Some(SystemTime::now());
+        }
+    }
+
+    // * Whether the restart policy asks for this finished task to run again.
+    // * Tasks stopped through the API are never restarted.
+    pub fn should_restart(&self) -> bool {
+        let wanted = match self.restart_policy {
+            RestartPolicy::Never => false,
+            RestartPolicy::OnFailure => self.state == State::Failed,
+            RestartPolicy::Always => {
+                self.state == State::Failed
+                    || (self.state == State::Completed && self.exit_code.is_some())
+            }
+        };
+
+        wanted && self.restarts_left()
+    }
+
+    // * Whether a failure of the task would still be restarted
+    pub fn restarts_left(&self) -> bool {
+        self.restart_policy != RestartPolicy::Never
+            && (self.max_restarts == 0 || self.restart_count < self.max_restarts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_transition_the_table_rejects_is_not_recorded() {
+        let mut task = Task::default();
+        task.transition(State::Cancelled, "stopped before it was placed");
+
+        assert!(!task.transition(State::Running, "container started"));
+        assert_eq!(task.state, State::Cancelled);
+        assert_eq!(task.history.len(), 1);
+    }
+
+    #[test]
+    fn an_unhealthy_completed_task_counts_as_failed() {
+        let mut task = Task {
+            state: State::Completed,
+            exit_code: Some(0),
+            ..Task::default()
+        };
+        task.exited(None, "stopped after failing its health check");
+
+        assert_eq!(task.state, State::Failed);
+        assert_eq!(task.exit_code, None);
+    }
+
+    #[test]
+    fn recorded_history_only_holds_moves_the_table_allows() {
+        let mut task = Task::default();
+        task.transition(State::Pending, "waiting for a worker: no workers");
+        task.transition(State::Scheduled, "placed on worker w1");
+        task.transition(State::Running, "container c1 started");
+        task.exited(Some(0), "container exited");
+        task.transition(State::Stopping, "stop requested through the manager API");
+
+        assert_eq!(task.state, State::Completed);
+        assert!(
+            task.history
+                .iter()
+                .all(|transition| valid_state_transition(&transition.from, &transition.to))
         );
-        map.insert(State::Completed, vec![]);
-        map.insert(State::Failed, vec![]);
-        map
-    };
-
-    if let Some(valid_states) = state_transition_map.get(&src) {
-        valid_states.contains(&dst)
-    } else {
-        false
     }
 }
@@ -1,4 +1,8 @@
-use std::{collections::HashMap, error::Error, fmt};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    error::Error,
+    fmt,
+};
 
 use bollard::Docker;
 use error_stack;
@@ -9,24 +13,91 @@ pub enum State {
     Pending,
     Scheduled,
     Running,
+    // * A stop was requested and the container has not stopped yet
+    Stopping,
+    // * The task is about to get a new container, either because the manager
+    // * restarts it or because its container is being replaced
+    Restarting,
     Completed,
     Failed,
+    // * Stopped before it ever got a container
+    Cancelled,
+    // * The worker holding the task no longer reports it
+    Unknown,
 }
 
+// * Transition is one entry of a task's state history
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Transition {
+    pub timestamp: std::time::SystemTime,
+    pub from: State,
+    pub to: State,
+    pub reason: String,
+}
+
+// * Fields missing from a submitted task take their default value
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Task {
     pub id: String,
     pub container_id: Option<String>,
     pub name: String,
     pub state: State,
     pub image: String,
+    // * Overrides the image entrypoint when not empty
+    pub command: Vec<String>,
+    // * Overrides the image CMD when not empty
+    pub args: Vec<String>,
+    // * KEY=VALUE pairs
+    pub env: Vec<String>,
+    // * Number of CPUs, fractions allowed
+    pub cpu: f64,
+    // * Memory limit in bytes
     pub memory: u64,
+    // * Disk the task needs in bytes, used for placement only
     pub disk: u64,
     pub exposed_ports: Vec<u16>,
-    pub port_bindings: HashMap<String, String>,
-    pub restart_policy: String,
+    // * Host ports requested for the task's container ports
+    pub port_bindings: Vec<PortBinding>,
+    // * Host ports the runtime actually assigned, reported once the task runs
+    pub assigned_ports: Vec<PortBinding>,
+    pub working_dir: String,
+    pub user: String,
+    // * Free-form key/value pairs describing the task
+    pub labels: HashMap<String, String>,
+    // * Service the task is a replica of, None for standalone tasks
+    pub service_id: Option<String>,
+    // * Where the task may and would rather run
+    pub placement: Placement,
+    pub restart_policy: RestartPolicy,
+    pub pull_policy: PullPolicy,
+    // * Progress of the last image pull for the task, one event per layer
+    // * and status
+    pub pull_events: Vec<PullEvent>,
+    // * Restarts the policy allows, 0 for no limit
+    pub max_restarts: u32,
+    // * Times the manager has started the task again
+    pub restart_count: u32,
+    // * Exit code of a container that exited on its own. None while the task
+    // * runs and when it was stopped through the API.
+    pub exit_code: Option<i64>,
+    pub health_check: Option<HealthCheck>,
+    // * Result of the health check, Unknown until it has been probed
+    pub health: HealthStatus,
+    // * Consecutive failed probes
+    pub health_failures: u32,
+    // * Every state change of the task, oldest first
+    pub history: Vec<Transition>,
+    // * Bumped by the manager on every stop, update and restart it makes.
+    // * Workers echo it back, so reports from before the change are told
+    // * apart without comparing clocks.
+    pub generation: u64,
     pub start_time: Option<std::time::SystemTime>,
     pub finish_time: Option<std::time::SystemTime>,
+    // * When the manager first saw the current run Running, on the manager's
+    // * clock. Workers set start_time before pulling the image, so this is
+    // * what rollouts measure health_wait from.
+    pub running_since: Option<std::time::SystemTime>,
 }
 
 impl Default for Task {
@@ -37,84 +108,569 @@ impl Default for Task {
             name: String::new(),
             state: State::Pending,
             image: String::new(),
+            command: Vec::new(),
+            args: Vec::new(),
+            env: Vec::new(),
+            cpu: 0.0,
             memory: 0,
             disk: 0,
             exposed_ports: Vec::new(),
-            port_bindings: HashMap::new(),
-            restart_policy: String::new(),
+            port_bindings: Vec::new(),
+            assigned_ports: Vec::new(),
+            working_dir: String::new(),
+            user: String::new(),
+            labels: HashMap::new(),
+            service_id: None,
+            placement: Placement::default(),
+            restart_policy: RestartPolicy::default(),
+            pull_policy: PullPolicy::default(),
+            pull_events: Vec::new(),
+            max_restarts: 0,
+            restart_count: 0,
+            exit_code: None,
+            health_check: None,
+            health: HealthStatus::default(),
+            health_failures: 0,
+            history: Vec::new(),
+            generation: 0,
             start_time: None,
             finish_time: None,
+            running_since: None,
         }
     }
 }
 
-impl Task {}
+impl Task {
+    // * Name for the task's container. It embeds the task id, so two tasks
+    // * with the same name never fight over a container name.
+    pub fn container_name(&self) -> String {
+        let name: String = format!("{}-{}", self.name, self.id)
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '-' {
+                    c
+                } else {
+                    '-'
+                }
+            })
+            .collect();
+        // Container names have to start with a letter or digit
+        format!("task-{}", name.trim_start_matches(['_', '.', '-']))
+    }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+    // * Records a pull event. Progress updates for a layer replace the last
+    // * event while its status stays the same, so only status changes pile up.
+    pub fn record_pull(&mut self, event: PullEvent) {
+        if let Some(last) = self
+            .pull_events
+            .iter_mut()
+            .rev()
+            .find(|last| last.layer.is_some() && last.layer == event.layer)
+            && last.status == event.status
+        {
+            *last = event;
+            return;
+        }
+        self.pull_events.push(event);
+    }
+
+    // * Returns this task with the spec fields of `spec`, keeping the id and
+    // * everything the runtime reported
+    pub fn with_spec(&self, spec: &Task) -> Task {
+        Task {
+            name: spec.name.clone(),
+            image: spec.image.clone(),
+            command: spec.command.clone(),
+            args: spec.args.clone(),
+            env: spec.env.clone(),
+            cpu: spec.cpu,
+            memory: spec.memory,
+            disk: spec.disk,
+            exposed_ports: spec.exposed_ports.clone(),
+            port_bindings: spec.port_bindings.clone(),
+            working_dir: spec.working_dir.clone(),
+            user: spec.user.clone(),
+            labels: spec.labels.clone(),
+            placement: spec.placement.clone(),
+            restart_policy: spec.restart_policy,
+            pull_policy: spec.pull_policy,
+            max_restarts: spec.max_restarts,
+            health_check: spec.health_check.clone(),
+            ..self.clone()
+        }
+    }
+
+    // * Whether `spec` would leave the spec fields of this task as they are
+    pub fn same_spec(&self, spec: &Task) -> bool {
+        serde_json::to_value(self.with_spec(spec)).ok() == serde_json::to_value(self).ok()
+    }
+}
+
+// * Placement restricts the workers a task is placed on. Every rule is a
+// * label selector, either "key=value" or just "key" for any value. Required
+// * and forbidden selectors match the worker's labels and rule workers out;
+// * affinity selectors match the labels of the tasks already on a worker and
+// * only make it more or less preferred.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Placement {
+    // * The worker must match all of these
+    pub required: Vec<String>,
+    // * The worker must match none of these
+    pub forbidden: Vec<String>,
+    // * Prefers workers running tasks that match these
+    pub affinity: Vec<String>,
+    // * Prefers workers running fewer tasks that match these
+    pub anti_affinity: Vec<String>,
+}
+
+// * RestartPolicy decides whether the manager starts a task again after its
+// * container exited. Docker's policy names are accepted too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum RestartPolicy {
+    #[default]
+    #[serde(rename = "never", alias = "no", alias = "")]
+    Never,
+    #[serde(rename = "on-failure")]
+    OnFailure,
+    #[serde(rename = "always", alias = "unless-stopped")]
+    Always,
+}
+
+// * PullPolicy decides when the worker pulls the task's image before running
+// * it. Kubernetes' names are accepted too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PullPolicy {
+    #[serde(rename = "always", alias = "Always")]
+    Always,
+    #[default]
+    #[serde(rename = "if-not-present", alias = "IfNotPresent", alias = "")]
+    IfNotPresent,
+    #[serde(rename = "never", alias = "Never")]
+    Never,
+}
+
+// * PullEvent is one step of an image pull as the registry reported it.
+// * Byte counts are only known while a layer downloads or extracts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PullEvent {
+    pub timestamp: std::time::SystemTime,
+    pub status: String,
+    pub layer: Option<String>,
+    pub current: Option<i64>,
+    pub total: Option<i64>,
+}
+
+pub type PullStream =
+    std::pin::Pin<Box<dyn futures_util::Stream<Item = RuntimeResult<PullEvent>> + Send>>;
+
+// * ImageInfo is an image a worker's runtime has cached, as served by
+// * GET /images
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImageInfo {
+    pub id: String,
+    pub tags: Vec<String>,
+    // * Bytes
+    pub size: i64,
+    // * Unix seconds
+    pub created: i64,
+}
+
+// * RegistryCredentials log a worker in to one registry. A worker loads them
+// * from its credentials file, keyed by registry host.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RegistryCredentials {
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub identity_token: Option<String>,
+}
+
+// * Manifest describes a task declaratively, as written in a YAML or TOML
+// * file. Applying a manifest creates the task with its name, or updates it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    pub name: String,
+    #[serde(default)]
+    pub image: String,
+    // * Overrides the image entrypoint when not empty
+    #[serde(default)]
+    pub command: Vec<String>,
+    // * Overrides the image CMD when not empty
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    #[serde(default)]
+    pub resources: ManifestResources,
+    #[serde(default)]
+    pub ports: Vec<ManifestPort>,
+    #[serde(default)]
+    pub restart: ManifestRestart,
+    #[serde(default)]
+    pub pull_policy: PullPolicy,
+    #[serde(default)]
+    pub health_check: Option<HealthCheck>,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    #[serde(default)]
+    pub placement: Placement,
+    #[serde(default)]
+    pub working_dir: String,
+    #[serde(default)]
+    pub user: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ManifestResources {
+    // * Number of CPUs, fractions allowed
+    pub cpu: f64,
+    pub memory: ByteSize,
+    // * Used for placement only
+    pub disk: ByteSize,
+}
+
+// * ByteSize is a number of bytes, or a string with a unit like "512Mi" or
+// * "2G"
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ByteSize {
+    Bytes(u64),
+    Text(String),
+}
+
+impl Default for ByteSize {
+    fn default() -> Self {
+        ByteSize::Bytes(0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ManifestPort {
+    pub container: u16,
+    // * Picked by the runtime when left out
+    #[serde(default)]
+    pub host: Option<u16>,
+    #[serde(default)]
+    pub protocol: Protocol,
+    #[serde(default)]
+    pub host_ip: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ManifestRestart {
+    pub policy: RestartPolicy,
+    // * 0 for no limit
+    pub max_restarts: u32,
+}
+
+// * ManifestError points at what is wrong in a manifest. `field` is the path
+// * to the offending field, like "ports[1].host", and empty when the manifest
+// * could not be read at all.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestError {
+    // * File, or manifest name, the error was found in
+    pub source: String,
+    pub field: String,
+    pub message: String,
+}
+
+// * HealthCheck is probed by the worker while the task runs. A task that
+// * fails `failure_threshold` probes in a row is unhealthy.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HealthCheck {
+    #[serde(flatten)]
+    pub probe: HealthProbe,
+    // * Seconds between two probes
+    #[serde(default = "HealthCheck::default_interval")]
+    pub interval: u64,
+    // * Seconds a probe may take before it counts as failed
+    #[serde(default = "HealthCheck::default_timeout")]
+    pub timeout: u64,
+    #[serde(default = "HealthCheck::default_failure_threshold")]
+    pub failure_threshold: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum HealthProbe {
+    // * GET on a container port, healthy on a 2xx or 3xx response
+    Http {
+        port: u16,
+        #[serde(default = "HealthProbe::default_path")]
+        path: String,
+    },
+    // * Healthy when a connection to the container port is accepted
+    Tcp {
+        port: u16,
+    },
+    // * Runs the command in the container, healthy when it exits with 0
+    Exec {
+        command: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum HealthStatus {
+    #[default]
+    Unknown,
+    Healthy,
+    Unhealthy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    #[default]
+    Tcp,
+    Udp,
+    Sctp,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortBinding {
+    pub container_port: u16,
+    #[serde(default)]
+    pub protocol: Protocol,
+    // * Host interface to bind, all interfaces when None
+    #[serde(default)]
+    pub host_ip: Option<String>,
+    // * Host port to bind, picked by the runtime when None
+    #[serde(default)]
+    pub host_port: Option<u16>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct TaskEvent {
     pub task_id: String,
-    pub event_type: String,
+    #[serde(flatten)]
+    pub kind: EventKind,
     pub timestamp: Option<std::time::SystemTime>,
     pub task: Task,
 }
 
-impl Default for TaskEvent {
-    fn default() -> Self {
-        TaskEvent {
-            task_id: String::new(),
-            event_type: String::new(),
-            timestamp: None,
-            task: Task::default(),
-        }
-    }
+// * EventKind is what a TaskEvent asks for, sent as its "event_type" field
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(tag = "event_type", rename_all = "lowercase")]
+pub enum EventKind {
+    // * Runs a new task
+    #[default]
+    Start,
+    // * Stops the task's container
+    Stop,
+    // * Replaces the task's container with a new one from the same spec, or
+    // * starts a finished task again
+    Restart,
+    // * Replaces the task's container with one from the spec in the event
+    Update,
+    // * Sends a signal such as "SIGHUP" to the task's container
+    Signal {
+        signal: String,
+    },
 }
 
 #[derive(Debug, Clone, Default)]
 pub struct Config {
     pub name: String,
-    pub attach_stdin: bool,
-    pub attach_stdout: bool,
-    pub attach_stderr: bool,
+    pub pull_policy: PullPolicy,
     pub exposed_ports: HashMap<String, HashMap<String, String>>,
+    pub port_bindings: Vec<PortBinding>,
+    pub entrypoint: Vec<String>,
     pub cmd: Vec<String>,
     pub image: String,
     pub cpu: f64,
     pub memory: i64,
-    pub disk: i64,
     pub env: Vec<String>,
-    pub restart_policy: String,
+    pub working_dir: String,
+    pub user: String,
 }
 
 pub fn new_config(task: Task) -> Config {
-    return Config {
-        name: task.name,
+    // A bound port has to be exposed for the binding to take effect
+    let exposed_ports = task
+        .exposed_ports
+        .iter()
+        .map(|port| format!("{}/tcp", port))
+        .chain(task.port_bindings.iter().map(PortBinding::port_key))
+        .map(|port| (port, HashMap::new()))
+        .collect();
+
+    Config {
+        name: task.container_name(),
+        pull_policy: task.pull_policy,
         image: task.image,
-        restart_policy: task.restart_policy,
-        ..Default::default()
-    };
+        entrypoint: task.command,
+        cmd: task.args,
+        env: task.env,
+        cpu: task.cpu,
+        memory: task.memory as i64,
+        exposed_ports,
+        port_bindings: task.port_bindings,
+        working_dir: task.working_dir,
+        user: task.user,
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct DockerClient {
     pub client: Docker,
+    // * Keyed by registry host, like "ghcr.io" or "docker.io"
+    pub credentials: HashMap<String, RegistryCredentials>,
+}
+
+// * MemoryRuntime keeps containers as plain records and never runs anything,
+// * so workers can be exercised on hosts without a container engine
+#[derive(Debug, Default)]
+pub struct MemoryRuntime {
+    pub images: std::sync::Mutex<HashSet<String>>,
+    // * Next port handed out for bindings without a host port
+    pub next_port: std::sync::atomic::AtomicU16,
+    pub containers: std::sync::Mutex<HashMap<String, MemoryContainer>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MemoryContainer {
+    pub status: ContainerStatus,
+    pub exit_code: Option<i64>,
+    pub ports: Vec<PortBinding>,
+}
+
+// * ProcessRuntime runs the task's command as a local child process instead
+// * of a container; the image is ignored
+#[derive(Debug, Default)]
+pub struct ProcessRuntime {
+    pub processes: tokio::sync::Mutex<HashMap<String, ProcessContainer>>,
+}
+
+#[derive(Debug)]
+pub struct ProcessContainer {
     pub config: Config,
+    pub child: Option<tokio::process::Child>,
+    pub exit_code: Option<i64>,
+    pub logs: std::sync::Arc<std::sync::Mutex<ProcessLogs>>,
+}
+
+// * ProcessLogs keeps the latest lines a process printed, with the time
+// * they were read, and hands new lines to anyone following them
+#[derive(Debug)]
+pub struct ProcessLogs {
+    pub lines: std::collections::VecDeque<(std::time::SystemTime, LogLine)>,
+    // * Bytes of the kept lines
+    pub bytes: usize,
+    // * Dropped once the process closed all its outputs
+    pub live: Option<tokio::sync::broadcast::Sender<LogLine>>,
+    pub open_streams: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ContainerStatus {
+    Created,
+    Running,
+    Exited,
+    Unknown,
+}
+
+// * ContainerInspect is the runtime-agnostic view of a container's state
+#[derive(Debug, Clone)]
+pub struct ContainerInspect {
+    pub status: ContainerStatus,
+    pub exit_code: Option<i64>,
+    // * Published ports with the host port the runtime assigned
+    pub ports: Vec<PortBinding>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+// * LogLine is one line a container printed, without its line break
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogLine {
+    pub stream: OutputStream,
+    pub line: String,
+}
+
+// * LogOptions selects which of a container's logs to read, as passed in the
+// * query of GET /tasks/{id}/logs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogOptions {
+    // * Keeps streaming new lines until the container exits
+    pub follow: bool,
+    // * Only the last lines of the existing logs
+    pub tail: Option<usize>,
+    // * Only lines printed at or after this Unix timestamp in seconds
+    pub since: Option<u64>,
+    pub stdout: bool,
+    pub stderr: bool,
+}
+
+impl Default for LogOptions {
+    fn default() -> Self {
+        LogOptions {
+            follow: false,
+            tail: None,
+            since: None,
+            stdout: true,
+            stderr: true,
+        }
+    }
+}
+
+pub type LogStream =
+    std::pin::Pin<Box<dyn futures_util::Stream<Item = RuntimeResult<LogLine>> + Send>>;
+
+// * ExecOutput is what a command run inside a container printed, and how it
+// * exited
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExecOutput {
+    pub exit_code: i64,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+pub type ExecStream = std::pin::Pin<
+    Box<dyn futures_util::Stream<Item = RuntimeResult<(OutputStream, Vec<u8>)>> + Send>,
+>;
+
+// * ExecSession is a command started inside a container. Whatever is written
+// * to `input` reaches the command's stdin, `output` yields what it prints as
+// * it prints it, and `exit` resolves to its exit code once `output` is done.
+pub struct ExecSession {
+    pub input: std::pin::Pin<Box<dyn tokio::io::AsyncWrite + Send>>,
+    pub output: ExecStream,
+    pub exit: std::pin::Pin<Box<dyn std::future::Future<Output = RuntimeResult<i64>> + Send>>,
 }
 
 // * DockerResponse is a simplified response type for Docker operations
 #[derive(Debug)]
 pub struct DockerResponse {
-    pub error: Option<DockerError>,
     pub action: Option<String>,
     pub container_id: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[allow(clippy::enum_variant_names)]
 pub enum DockerError {
     ClientError(String),
     ImagePullError(String),
+    ImageNotFoundError(String),
+    ImageListError(String),
     ContainerCreationError(String),
     ContainerStartError(String),
     ContainerStopError(String),
+    ContainerInspectError(String),
+    ContainerRemoveError(String),
+    ContainerExecError(String),
+    ContainerKillError(String),
+    ContainerLogsError(String),
 }
 
 impl fmt::Display for DockerError {
@@ -122,11 +678,22 @@ impl fmt::Display for DockerError {
         match self {
             DockerError::ClientError(msg) => write!(f, "Docker client error: {}", msg),
             DockerError::ImagePullError(msg) => write!(f, "Docker image pull error: {}", msg),
+            DockerError::ImageNotFoundError(msg) => write!(f, "Image not found: {}", msg),
+            DockerError::ImageListError(msg) => write!(f, "Image list error: {}", msg),
             DockerError::ContainerCreationError(msg) => {
                 write!(f, "Container creation error: {}", msg)
             }
             DockerError::ContainerStartError(msg) => write!(f, "Container start error: {}", msg),
             DockerError::ContainerStopError(msg) => write!(f, "Container stop error: {}", msg),
+            DockerError::ContainerInspectError(msg) => {
+                write!(f, "Container inspect error: {}", msg)
+            }
+            DockerError::ContainerRemoveError(msg) => {
+                write!(f, "Container remove error: {}", msg)
+            }
+            DockerError::ContainerExecError(msg) => write!(f, "Container exec error: {}", msg),
+            DockerError::ContainerKillError(msg) => write!(f, "Container kill error: {}", msg),
+            DockerError::ContainerLogsError(msg) => write!(f, "Container logs error: {}", msg),
         }
     }
 }
@@ -134,3 +701,5 @@ impl fmt::Display for DockerError {
 impl Error for DockerError {}
 
 pub type DockerResult = Result<DockerResponse, error_stack::Report<DockerError>>;
+
+pub type RuntimeResult<T> = Result<T, error_stack::Report<DockerError>>;
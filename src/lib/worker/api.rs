@@ -1,23 +1,29 @@
 use axum::{
     Json, Router,
-    extract::{Path, State as AxumState},
-    http::StatusCode,
-    response::IntoResponse,
+    body::Body,
+    extract::{Path, Query, State as AxumState, ws::WebSocketUpgrade},
+    http::{HeaderMap, StatusCode, header},
+    response::{
+        IntoResponse, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
     routing::{delete, get, post},
 };
+use futures_util::StreamExt;
 
-use super::types::{TaskServer, Worker};
-use crate::lib::tasks::types::{Task, TaskEvent};
+use super::exec::exec_socket;
+use super::types::{ExecRequest, HumanStats, StatsQuery, TaskServer, Worker};
+use crate::lib::tasks::types::{EventKind, LogOptions, LogStream, OutputStream, Task, TaskEvent};
 use crate::lib::{
-    tasks::types::State,
-    worker::{stats::get_stats, types::SystemStats},
+    tasks::{runtime::Runtime, state::valid_state_transition, types::State},
+    worker::worker::get_system_stats,
 };
 use std::sync::Arc;
 use tokio::net::TcpListener;
 use tokio::sync::Mutex;
 
-impl TaskServer {
-    pub fn new(worker: Arc<Mutex<Worker>>, address: &str, port: &str) -> Self {
+impl<R: Runtime> TaskServer<R> {
+    pub fn new(worker: Arc<Mutex<Worker<R>>>, address: &str, port: &str) -> Self {
         Self {
             worker,
             address: address.to_string(),
@@ -25,30 +31,40 @@ impl TaskServer {
         }
     }
 
-    async fn get_tasks(AxumState(server): AxumState<Arc<Mutex<TaskServer>>>) -> Json<Vec<Task>> {
+    async fn get_tasks(AxumState(server): AxumState<Arc<Mutex<TaskServer<R>>>>) -> Json<Vec<Task>> {
         let worker = server.lock().await.worker.clone();
         let tasks = worker.lock().await.get_tasks();
         Json(tasks)
     }
 
     async fn start_task(
-        AxumState(server): AxumState<Arc<Mutex<TaskServer>>>,
+        AxumState(server): AxumState<Arc<Mutex<TaskServer<R>>>>,
         Json(task_event): Json<TaskEvent>,
     ) -> impl IntoResponse {
+        if let Err(e) = task_event.validate() {
+            return (StatusCode::BAD_REQUEST, e);
+        }
+
+        let status = match task_event.kind {
+            EventKind::Start => StatusCode::CREATED,
+            _ => StatusCode::ACCEPTED,
+        };
+        let message = format!("Task {} queued to {}", task_event.task.id, task_event.kind);
+        println!("{}: {:?}", message, task_event.task_id);
+
         let worker = server.lock().await.worker.clone();
-        worker.lock().await.add_task(task_event.task.clone());
-        println!("Task Queued to start: {:?}", task_event.task_id);
-        StatusCode::CREATED
+        worker.lock().await.add_event(task_event);
+        (status, message)
     }
 
     async fn stop_task(
-        AxumState(server): AxumState<Arc<Mutex<TaskServer>>>,
+        AxumState(server): AxumState<Arc<Mutex<TaskServer<R>>>>,
         Path(id): Path<String>,
     ) -> impl IntoResponse {
         let worker = server.lock().await.worker.clone();
         let mut guard = worker.lock().await;
-        let task = match guard.db.get(&id) {
-            Some(task) => task.as_ref().clone(),
+        let task = match guard.db.get(&id).ok().flatten() {
+            Some(task) => task,
             None => {
                 return (
                     StatusCode::NOT_FOUND,
@@ -57,21 +73,162 @@ impl TaskServer {
             }
         };
 
-        let mut stopped_task = task;
-        stopped_task.state = State::Completed;
-        guard.add_task(stopped_task);
-        println!("Task stopped: {:?}", id);
-        (StatusCode::OK, format!("Task with id {} stopped", id))
+        if !valid_state_transition(&task.state, &State::Stopping) {
+            return (
+                StatusCode::CONFLICT,
+                format!(
+                    "Task with id {} is {:?} and cannot be stopped",
+                    id, task.state
+                ),
+            );
+        }
+
+        guard.add_event(TaskEvent::new(EventKind::Stop, task));
+        println!("Task queued to stop: {:?}", id);
+        (StatusCode::OK, format!("Task with id {} stopping", id))
+    }
+
+    async fn get_task_logs(
+        AxumState(server): AxumState<Arc<Mutex<TaskServer<R>>>>,
+        Path(id): Path<String>,
+        Query(options): Query<LogOptions>,
+        headers: HeaderMap,
+    ) -> Response {
+        let worker = server.lock().await.worker.clone();
+        let (task, executor) = {
+            let worker = worker.lock().await;
+            (worker.db.get(&id).ok().flatten(), worker.executor())
+        };
+
+        let Some(task) = task else {
+            return (
+                StatusCode::NOT_FOUND,
+                format!("Task with id {} not found", id),
+            )
+                .into_response();
+        };
+        let Some(container_id) = task.container_id else {
+            return (
+                StatusCode::CONFLICT,
+                format!("Task with id {} has no container yet", id),
+            )
+                .into_response();
+        };
+
+        match executor.runtime.logs(&container_id, &options).await {
+            Ok(lines) => log_response(lines, &headers),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        }
+    }
+
+    // * Finds the runtime and container to exec into, or the response that
+    // * explains why there is none
+    async fn exec_target(
+        server: &Mutex<TaskServer<R>>,
+        id: &str,
+    ) -> Result<(Arc<R>, String), Response> {
+        let worker = server.lock().await.worker.clone();
+        let worker = worker.lock().await;
+        if !worker.allow_exec {
+            return Err((
+                StatusCode::FORBIDDEN,
+                "Exec is disabled on this worker".to_string(),
+            )
+                .into_response());
+        }
+
+        let Some(task) = worker.db.get(id).ok().flatten() else {
+            return Err((
+                StatusCode::NOT_FOUND,
+                format!("Task with id {} not found", id),
+            )
+                .into_response());
+        };
+        match task.container_id {
+            Some(container_id) if task.state == State::Running => {
+                Ok((worker.runtime.clone(), container_id))
+            }
+            _ => Err((
+                StatusCode::CONFLICT,
+                format!("Task with id {} is {:?} and not running", id, task.state),
+            )
+                .into_response()),
+        }
+    }
+
+    async fn exec_task(
+        AxumState(server): AxumState<Arc<Mutex<TaskServer<R>>>>,
+        Path(id): Path<String>,
+        Json(request): Json<ExecRequest>,
+    ) -> Response {
+        if request.command.is_empty() {
+            return (StatusCode::BAD_REQUEST, "Empty command".to_string()).into_response();
+        }
+        let (runtime, container_id) = match Self::exec_target(&server, &id).await {
+            Ok(target) => target,
+            Err(response) => return response,
+        };
+
+        println!("Running {:?} in task {}", request.command, id);
+        match runtime.exec(&container_id, &request.command).await {
+            Ok(output) => (StatusCode::OK, Json(output)).into_response(),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        }
+    }
+
+    async fn exec_task_socket(
+        AxumState(server): AxumState<Arc<Mutex<TaskServer<R>>>>,
+        Path(id): Path<String>,
+        upgrade: WebSocketUpgrade,
+    ) -> Response {
+        match Self::exec_target(&server, &id).await {
+            Ok((runtime, container_id)) => {
+                upgrade.on_upgrade(move |socket| exec_socket(socket, runtime, container_id))
+            }
+            Err(response) => response,
+        }
+    }
+
+    async fn get_task_history(
+        AxumState(server): AxumState<Arc<Mutex<TaskServer<R>>>>,
+        Path(id): Path<String>,
+    ) -> impl IntoResponse {
+        let worker = server.lock().await.worker.clone();
+        let task = worker.lock().await.db.get(&id).ok().flatten();
+        match task {
+            Some(task) => (StatusCode::OK, Json(task.history)).into_response(),
+            None => (
+                StatusCode::NOT_FOUND,
+                format!("Task with id {} not found", id),
+            )
+                .into_response(),
+        }
+    }
+
+    async fn get_images(AxumState(server): AxumState<Arc<Mutex<TaskServer<R>>>>) -> Response {
+        let worker = server.lock().await.worker.clone();
+        let runtime = worker.lock().await.runtime.clone();
+        match runtime.images().await {
+            Ok(mut images) => {
+                // Largest first, as those are the ones worth pruning
+                images.sort_by_key(|image| std::cmp::Reverse(image.size));
+                (StatusCode::OK, Json(images)).into_response()
+            }
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        }
     }
 
     pub async fn get_stats(
-        AxumState(server): AxumState<Arc<Mutex<TaskServer>>>,
+        AxumState(server): AxumState<Arc<Mutex<TaskServer<R>>>>,
+        Query(query): Query<StatsQuery>,
     ) -> impl IntoResponse {
-        let worker_guard = server.lock().await.worker.clone();
-        let mut worker_guard = worker_guard.lock().await;
-        worker_guard.sysinfo.refresh_all();
-        let stats = get_stats(&worker_guard.sysinfo, worker_guard.task_count);
-        (StatusCode::OK, Json(stats))
+        let worker = server.lock().await.worker.clone();
+        let stats = get_system_stats(worker).await;
+        if query.human {
+            (StatusCode::OK, Json(HumanStats(&stats))).into_response()
+        } else {
+            (StatusCode::OK, Json(stats)).into_response()
+        }
     }
 
     pub async fn start_server(self) {
@@ -81,10 +238,15 @@ impl TaskServer {
         println!("Starting TaskServer at {}:{}", address, port);
 
         let app = Router::new()
-            .route("/stats", get(TaskServer::get_stats))
-            .route("/tasks", get(TaskServer::get_tasks))
-            .route("/tasks", post(TaskServer::start_task))
-            .route("/tasks/{id}", delete(TaskServer::stop_task))
+            .route("/stats", get(Self::get_stats))
+            .route("/images", get(Self::get_images))
+            .route("/tasks", get(Self::get_tasks))
+            .route("/tasks", post(Self::start_task))
+            .route("/tasks/{id}", delete(Self::stop_task))
+            .route("/tasks/{id}/history", get(Self::get_task_history))
+            .route("/tasks/{id}/logs", get(Self::get_task_logs))
+            .route("/tasks/{id}/exec", post(Self::exec_task))
+            .route("/tasks/{id}/exec/ws", get(Self::exec_task_socket))
             .with_state(shared);
 
         println!("Listening on {}:{}", address, port);
@@ -95,3 +257,38 @@ impl TaskServer {
         axum::serve(listener, app).await.unwrap();
     }
 }
+
+// * Sends log lines as server-sent events when the client accepts them, with
+// * the output stream as the event name, and as chunked plain text otherwise
+fn log_response(lines: LogStream, headers: &HeaderMap) -> Response {
+    let wants_events = headers
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/event-stream"));
+
+    if wants_events {
+        let events = lines.map(|line| match line {
+            Ok(line) => {
+                let stream = match line.stream {
+                    OutputStream::Stdout => "stdout",
+                    OutputStream::Stderr => "stderr",
+                };
+                Ok(Event::default().event(stream).data(line.line))
+            }
+            Err(e) => Err(e.current_context().clone()),
+        });
+        return Sse::new(events)
+            .keep_alive(KeepAlive::default())
+            .into_response();
+    }
+
+    let body = lines.map(|line| {
+        line.map(|line| format!("{}\n", line.line))
+            .map_err(|e| e.current_context().clone())
+    });
+    (
+        [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+        Body::from_stream(body),
+    )
+        .into_response()
+}
@@ -0,0 +1,108 @@
+use axum::extract::ws::{Message, WebSocket};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use super::types::{ExecMessage, ExecRequest};
+use crate::lib::tasks::{runtime::Runtime, types::ExecSession};
+use futures_util::StreamExt;
+use std::sync::Arc;
+
+// * Runs an interactive command in a container over a WebSocket. The client
+// * first sends an ExecRequest as JSON, then every text or binary message is
+// * written to the command's stdin until the client closes it. Output comes
+// * back as ExecMessage frames, followed by the exit code.
+pub async fn exec_socket<R: Runtime>(mut socket: WebSocket, runtime: Arc<R>, container_id: String) {
+    let request = match socket.recv().await {
+        Some(Ok(Message::Text(text))) => serde_json::from_str::<ExecRequest>(text.as_str())
+            .map_err(|e| format!("Invalid exec request: {}", e)),
+        _ => Err("Expected an exec request as the first message".to_string()),
+    };
+    let request = match request {
+        Ok(request) if !request.command.is_empty() => request,
+        Ok(_) => return close_with_error(socket, "Empty command".to_string()).await,
+        Err(message) => return close_with_error(socket, message).await,
+    };
+
+    let session = match runtime
+        .exec_session(&container_id, &request.command, true)
+        .await
+    {
+        Ok(session) => session,
+        Err(e) => return close_with_error(socket, e.to_string()).await,
+    };
+    println!(
+        "Running {:?} interactively in container {}",
+        request.command, container_id
+    );
+
+    let ExecSession {
+        mut input,
+        mut output,
+        exit,
+    } = session;
+    let mut stdin_open = true;
+    loop {
+        tokio::select! {
+            chunk = output.next() => match chunk {
+                Some(Ok((stream, data))) => {
+                    let message = ExecMessage::Output {
+                        stream,
+                        data: String::from_utf8_lossy(&data).into_owned(),
+                    };
+                    if send(&mut socket, &message).await.is_err() {
+                        return;
+                    }
+                }
+                Some(Err(e)) => return close_with_error(socket, e.to_string()).await,
+                None => break,
+            },
+            message = socket.recv(), if stdin_open => {
+                let written = match message {
+                    Some(Ok(Message::Text(text))) => write_input(&mut input, text.as_bytes()).await,
+                    Some(Ok(Message::Binary(data))) => write_input(&mut input, &data).await,
+                    Some(Ok(Message::Ping(_) | Message::Pong(_))) => Ok(()),
+                    // A closed socket is the command's end of input
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => {
+                        stdin_open = false;
+                        input.shutdown().await
+                    }
+                };
+                if let Err(e) = written {
+                    println!("Error writing to exec in container {}: {}", container_id, e);
+                    stdin_open = false;
+                }
+            }
+        }
+    }
+
+    let message = match exit.await {
+        Ok(exit_code) => ExecMessage::Exit { exit_code },
+        Err(e) => ExecMessage::Error {
+            message: e.to_string(),
+        },
+    };
+    if send(&mut socket, &message).await.is_ok() {
+        let _ = socket.send(Message::Close(None)).await;
+    }
+}
+
+async fn write_input(
+    input: &mut (impl AsyncWrite + Unpin + ?Sized),
+    data: &[u8],
+) -> std::io::Result<()> {
+    input.write_all(data).await?;
+    input.flush().await
+}
+
+async fn send(socket: &mut WebSocket, message: &ExecMessage) -> Result<(), axum::Error> {
+    let text = serde_json::to_string(message).unwrap_or_default();
+    socket.send(Message::Text(text.into())).await
+}
+
+async fn close_with_error(mut socket: WebSocket, message: String) {
+    if send(&mut socket, &ExecMessage::Error { message })
+        .await
+        .is_ok()
+    {
+        let _ = socket.send(Message::Close(None)).await;
+    }
+}
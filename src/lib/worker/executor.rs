@@ -0,0 +1,280 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant, SystemTime},
+};
+
+use error_stack::Report;
+
+use super::types::{Executor, WorkerError};
+use crate::lib::{
+    store::store::Store,
+    tasks::{
+        runtime::Runtime,
+        types::{
+            DockerError, DockerResponse, DockerResult, EventKind, HealthStatus, State, Task,
+            TaskEvent, new_config,
+        },
+    },
+};
+
+// * Saves the task. A task recorded as running for the first time counts
+// * towards `task_count` and one that stops running no longer does.
+pub fn persist(
+    db: &dyn Store<Task>,
+    task_count: &AtomicU64,
+    task: &Task,
+) -> Result<(), Report<DockerError>> {
+    let was_running = db
+        .get(&task.id)
+        .ok()
+        .flatten()
+        .is_some_and(|stored| stored.state == State::Running);
+    db.put(&task.id, task.clone()).map_err(|e| {
+        Report::new(WorkerError::StoreError(e.to_string())).change_context(
+            DockerError::ClientError(format!("Unable to save task {}", task.id)),
+        )
+    })?;
+
+    match (was_running, task.state == State::Running) {
+        (false, true) => {
+            task_count.fetch_add(1, Ordering::Relaxed);
+        }
+        (true, false) => {
+            task_count.fetch_sub(1, Ordering::Relaxed);
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+impl<R: Runtime> Clone for Executor<R> {
+    fn clone(&self) -> Self {
+        Executor {
+            runtime: self.runtime.clone(),
+            db: self.db.clone(),
+            task_count: self.task_count.clone(),
+            remove_on_stop: self.remove_on_stop,
+        }
+    }
+}
+
+impl<R: Runtime> Executor<R> {
+    fn persist(&self, task: &Task) -> Result<(), Report<DockerError>> {
+        persist(self.db.as_ref(), &self.task_count, task)
+    }
+
+    // * Carries out an event the worker already validated and recorded
+    pub async fn execute(&self, event: TaskEvent) -> DockerResult {
+        let task = event.task;
+        match event.kind {
+            EventKind::Start | EventKind::Restart | EventKind::Update => {
+                println!("Task {} is {:?}, starting it now", task.id, task.state);
+                self.start_task(task).await
+            }
+            EventKind::Stop => {
+                println!("Task is stopping, stopping it now");
+                self.stop_task(task).await
+            }
+            EventKind::Signal { signal } => self.signal_task(task, &signal).await,
+        }
+    }
+
+    // * Starts a container for the task, replacing the one it had before
+    async fn start_task(&self, mut task: Task) -> DockerResult {
+        if let Some(previous) = task.container_id.take() {
+            println!("Replacing container {} of task {}", previous, task.id);
+            if let Err(err) = self.runtime.stop(&previous).await {
+                println!("Error stopping container {}: {:?}", previous, err);
+            }
+            // Frees the container name for the new one
+            if let Err(err) = self.runtime.remove(&previous).await {
+                println!("Error removing container {}: {:?}", previous, err);
+            }
+        }
+
+        task.start_time = Some(SystemTime::now());
+        task.finish_time = None;
+        task.exit_code = None;
+        task.assigned_ports = Vec::new();
+        task.health = HealthStatus::Unknown;
+        task.health_failures = 0;
+
+        task.pull_events = Vec::new();
+
+        let config = new_config(task.clone());
+
+        // Pull progress is saved at most once a second, so the task shows it
+        // while a large image downloads
+        let mut last_saved = Instant::now();
+        let result = self
+            .runtime
+            .run(&config, |event| {
+                task.record_pull(event);
+                if last_saved.elapsed() >= Duration::from_secs(1) {
+                    last_saved = Instant::now();
+                    if let Err(err) = self.persist(&task) {
+                        println!("Error saving pull progress of {}: {:?}", task.id, err);
+                    }
+                }
+            })
+            .await;
+        match result {
+            Ok(response) => {
+                println!(
+                    "Task started successfully with container ID: {:?}",
+                    response.container_id
+                );
+
+                if let Some(container_id) = response.container_id.clone() {
+                    task.transition(
+                        State::Running,
+                        format!("container {} started", container_id),
+                    );
+                    match self.runtime.inspect(&container_id).await {
+                        Ok(inspect) => task.assigned_ports = inspect.ports,
+                        Err(err) => {
+                            println!(
+                                "Unable to read assigned ports of task {}: {:?}",
+                                task.id, err
+                            )
+                        }
+                    }
+                    task.container_id = Some(container_id);
+                    self.persist(&task)?;
+                }
+                Ok(response)
+            }
+            Err(err) => {
+                println!("Error running task: {:?}", err);
+                task.transition(State::Failed, format!("failed to start: {}", err));
+                task.finish_time = Some(SystemTime::now());
+                if let Err(store_err) = self.persist(&task) {
+                    println!("Error saving failed task {}: {:?}", task.id, store_err);
+                }
+                Err(err)
+            }
+        }
+    }
+
+    async fn stop_task(&self, mut task: Task) -> DockerResult {
+        let container_id = match task.container_id.clone() {
+            Some(id) => id,
+            // Nothing was started, so there is nothing to stop
+            None => {
+                println!("Task {} has no container, cancelling it", task.id);
+                task.transition(State::Cancelled, "stopped before it was started");
+                task.finish_time = Some(SystemTime::now());
+                self.persist(&task)?;
+
+                return Ok(DockerResponse {
+                    action: Some("Cancel".to_string()),
+                    container_id: None,
+                });
+            }
+        };
+
+        let result = self.runtime.stop(&container_id).await;
+        match result {
+            Ok(()) => {
+                let response = DockerResponse {
+                    action: Some("Stop".to_string()),
+                    container_id: Some(container_id.clone()),
+                };
+                task.transition(State::Completed, "stopped on request");
+                task.finish_time = Some(SystemTime::now());
+
+                if self.remove_on_stop {
+                    match self.runtime.remove(&container_id).await {
+                        Ok(()) => task.container_id = None,
+                        Err(err) => {
+                            println!("Error removing container {}: {:?}", container_id, err)
+                        }
+                    }
+                }
+
+                self.persist(&task)?;
+                println!(
+                    "Stopped task {} with container ID: {:?}",
+                    task.id, response.container_id
+                );
+
+                Ok(response)
+            }
+            // The container is still up, so the task is running again and a
+            // later stop can be tried
+            Err(err) => {
+                println!("Error stopping task: {:?}", err);
+                task.transition(State::Running, format!("failed to stop: {}", err));
+                if let Err(store_err) = self.persist(&task) {
+                    println!("Error saving task {}: {:?}", task.id, store_err);
+                }
+                Err(err)
+            }
+        }
+    }
+
+    async fn signal_task(&self, task: Task, signal: &str) -> DockerResult {
+        let container_id = task.container_id.clone().ok_or_else(|| {
+            Report::new(WorkerError::DockerClientError(format!(
+                "Task {} has no container to signal",
+                task.id
+            )))
+            .change_context(DockerError::ClientError(format!(
+                "Cannot send {} to task {}",
+                signal, task.id
+            )))
+        })?;
+
+        self.runtime.kill(&container_id, signal).await?;
+        println!("Sent {} to task {}", signal, task.id);
+        Ok(DockerResponse {
+            action: Some("Signal".to_string()),
+            container_id: Some(container_id),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::lib::{
+        store::types::InMemoryStore,
+        tasks::{state::valid_state_transition, types::MemoryRuntime},
+    };
+
+    #[tokio::test]
+    async fn a_failed_stop_leaves_the_task_running_so_it_can_be_stopped_again() {
+        let executor = Executor {
+            runtime: Arc::new(MemoryRuntime::new()),
+            db: Arc::new(InMemoryStore::new()),
+            task_count: Arc::new(AtomicU64::new(0)),
+            remove_on_stop: false,
+        };
+        let mut task = Task {
+            state: State::Running,
+            container_id: Some("missing".to_string()),
+            ..Task::default()
+        };
+        task.transition(State::Stopping, "stop requested through the manager API");
+        executor.persist(&task).unwrap();
+
+        let result = executor
+            .execute(TaskEvent::new(EventKind::Stop, task.clone()))
+            .await;
+
+        assert!(result.is_err());
+        let stored = executor.db.get(&task.id).unwrap().unwrap();
+        assert_eq!(stored.state, State::Running);
+        assert!(
+            stored
+                .history
+                .last()
+                .unwrap()
+                .reason
+                .starts_with("failed to stop")
+        );
+        assert!(valid_state_transition(&stored.state, &State::Stopping));
+    }
+}
@@ -0,0 +1,131 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use futures_util::future::join_all;
+use tokio::sync::Mutex;
+
+use super::types::Worker;
+use crate::lib::tasks::{
+    runtime::Runtime,
+    types::{HealthStatus, State, Task},
+};
+
+// * Probes the health checks of running tasks, each on its own interval. A
+// * task that turns unhealthy is stopped and failed when its restart policy
+// * can replace it, otherwise it keeps running and is only reported.
+pub async fn check_health<R: Runtime>(worker: Arc<Mutex<Worker<R>>>) {
+    let mut next_probe: HashMap<String, Instant> = HashMap::new();
+
+    loop {
+        tokio::time::sleep(Duration::from_secs(1)).await;
+
+        let now = Instant::now();
+        let (executor, tasks) = {
+            let worker = worker.lock().await;
+            let tasks: Vec<Task> = worker
+                .get_tasks()
+                .into_iter()
+                .filter(|task| {
+                    task.state == State::Running
+                        && task.health_check.is_some()
+                        && !worker.running.contains(&task.id)
+                })
+                .collect();
+            (worker.executor(), tasks)
+        };
+
+        next_probe.retain(|id, _| tasks.iter().any(|task| &task.id == id));
+        let due: Vec<Task> = tasks
+            .into_iter()
+            .filter(|task| {
+                let check = task.health_check.as_ref().unwrap();
+                // The first probe waits one interval so the task can start up
+                let next = next_probe
+                    .entry(task.id.clone())
+                    .or_insert_with(|| now + Duration::from_secs(check.interval));
+                if *next > now {
+                    return false;
+                }
+                *next = now + Duration::from_secs(check.interval);
+                true
+            })
+            .collect();
+
+        let results = join_all(due.iter().map(|task| {
+            let check = task.health_check.as_ref().unwrap();
+            check.probe(executor.runtime.as_ref(), task)
+        }))
+        .await;
+
+        for (task, result) in due.into_iter().zip(results) {
+            let unhealthy = {
+                let worker = worker.lock().await;
+                if worker.running.contains(&task.id) {
+                    continue;
+                }
+                let Ok(Some(mut current)) = worker.db.get(&task.id) else {
+                    continue;
+                };
+                // The task may have been stopped or restarted during the probe
+                if current.state != State::Running || current.container_id != task.container_id {
+                    continue;
+                }
+
+                let threshold = task.health_check.as_ref().unwrap().failure_threshold;
+                match result {
+                    Ok(()) => {
+                        current.health = HealthStatus::Healthy;
+                        current.health_failures = 0;
+                    }
+                    Err(e) => {
+                        current.health_failures += 1;
+                        println!(
+                            "Health check {}/{} failed for task {}: {}",
+                            current.health_failures, threshold, current.id, e
+                        );
+                        if current.health_failures >= threshold.max(1) {
+                            current.health = HealthStatus::Unhealthy;
+                        }
+                    }
+                }
+
+                if let Err(err) = worker.persist(&current) {
+                    println!("Error saving health of task {}: {:?}", current.id, err);
+                }
+                current.health == HealthStatus::Unhealthy && current.restarts_left()
+            };
+
+            if unhealthy {
+                replace_unhealthy(&worker, task).await;
+            }
+        }
+    }
+}
+
+async fn replace_unhealthy<R: Runtime>(worker: &Arc<Mutex<Worker<R>>>, task: Task) {
+    println!("Task {} is unhealthy, stopping it", task.id);
+    let executor = worker.lock().await.executor();
+    if let Some(container_id) = &task.container_id
+        && let Err(err) = executor.runtime.stop(container_id).await
+    {
+        println!("Error stopping unhealthy task {}: {:?}", task.id, err);
+    }
+
+    let worker = worker.lock().await;
+    let Ok(Some(mut current)) = worker.db.get(&task.id) else {
+        return;
+    };
+    if worker.running.contains(&task.id) || current.container_id != task.container_id {
+        return;
+    }
+    // Whatever the container exited with, it was stopped for being unhealthy
+    if current.state == State::Running || current.state == State::Completed {
+        current.exited(None, "stopped after failing its health check");
+        if let Err(err) = worker.persist(&current) {
+            println!("Error saving unhealthy task {}: {:?}", current.id, err);
+        }
+    }
+}
@@ -1,4 +1,9 @@
+pub mod api;
+pub mod exec;
+pub mod executor;
+pub mod health;
+pub mod registration;
+pub mod stats;
 pub mod types;
+#[allow(clippy::module_inception)]
 pub mod worker;
-pub mod api;
-pub mod stats;
\ No newline at end of file
@@ -0,0 +1,96 @@
+use std::{sync::Arc, time::Duration};
+
+use reqwest::StatusCode;
+use tokio::sync::Mutex;
+
+use super::{types::Worker, worker::get_system_stats};
+use crate::lib::{
+    manager::types::{WorkerCapacity, WorkerRegistration},
+    tasks::runtime::Runtime,
+};
+
+async fn register<R: Runtime>(
+    client: &reqwest::Client,
+    worker: &Arc<Mutex<Worker<R>>>,
+    manager: &str,
+    address: &str,
+) -> Result<(), String> {
+    let stats = get_system_stats(worker.clone()).await;
+    let (name, labels) = {
+        let worker = worker.lock().await;
+        (worker.name.clone(), worker.labels.clone())
+    };
+    let registration = WorkerRegistration {
+        name,
+        address: address.to_string(),
+        capacity: WorkerCapacity::from(&stats),
+        labels,
+    };
+
+    let url = format!("http://{}/workers", manager);
+    let response = client
+        .post(&url)
+        .json(&registration)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to connect to {}: {}", url, e))?;
+
+    if response.status().is_success() {
+        println!(
+            "Registered with manager {} as {}",
+            manager, registration.name
+        );
+        Ok(())
+    } else {
+        Err(format!(
+            "Manager {} rejected registration with status {}",
+            manager,
+            response.status().as_u16()
+        ))
+    }
+}
+
+// * Registers the worker with the manager and keeps sending heartbeats. If the
+// * manager forgets the worker (e.g. after a restart) it registers again.
+pub async fn register_with_manager<R: Runtime>(
+    worker: Arc<Mutex<Worker<R>>>,
+    manager: String,
+    address: String,
+    interval: Duration,
+) {
+    let client = reqwest::Client::new();
+    let mut registered = false;
+
+    loop {
+        if !registered {
+            match register(&client, &worker, &manager, &address).await {
+                Ok(()) => registered = true,
+                Err(e) => eprintln!("Error registering worker: {}", e),
+            }
+        } else {
+            let name = worker.lock().await.name.clone();
+            let url = format!("http://{}/workers/{}/heartbeat", manager, name);
+            match client.post(&url).send().await {
+                Ok(response) if response.status() == StatusCode::NOT_FOUND => {
+                    println!(
+                        "Manager {} does not know this worker, registering again",
+                        manager
+                    );
+                    registered = false;
+                    continue;
+                }
+                Ok(response) if !response.status().is_success() => {
+                    eprintln!(
+                        "Heartbeat rejected by manager {} with status {}",
+                        manager,
+                        response.status().as_u16()
+                    );
+                }
+                Ok(_) => {}
+                Err(e) => eprintln!("Error sending heartbeat to {}: {}", url, e),
+            }
+        }
+
+        tokio::time::sleep(interval).await;
+    }
+}
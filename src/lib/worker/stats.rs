@@ -1,50 +1,132 @@
-use serde::{ser::SerializeStruct, Serialize};
+use crate::lib::{
+    manager::types::WorkerCapacity,
+    worker::types::{HumanStats, SystemStats},
+};
+use serde::{Serialize, ser::SerializeStruct};
+use std::collections::HashMap;
 use sysinfo::{Disks, System};
-use crate::lib::worker::types::{SystemStats};
 
-impl Serialize for SystemStats {
+const MB: u64 = 1024 * 1024;
+
+impl Serialize for HumanStats<'_> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
-        let mut state = serializer.serialize_struct("SystemStats", 8)?;
-        state.serialize_field("cpu_usage", &format!("{:.2}%", self.cpu_usage))?;
-        state.serialize_field("total_memory", &format!("{} MB", self.total_memory))?;
-        state.serialize_field("used_memory", &format!("{} MB", self.used_memory))?;
-        state.serialize_field("total_swap", &format!("{} MB", self.total_swap))?;
-        state.serialize_field("used_swap", &format!("{} MB", self.used_swap))?;
-        state.serialize_field("system_name", &self.system_name)?;
-        state.serialize_field("hostname", &self.hostname)?;
-        state.serialize_field("total_cpus", &self.total_cpus)?;
-        state.serialize_field("disk_usage", &format!("{:.2}%", self.disk_usage))?;
-        state.serialize_field("task_count", &self.task_count)?;
+        let stats = self.0;
+        let mut state = serializer.serialize_struct("SystemStats", 13)?;
+        state.serialize_field("cpu_usage", &format!("{:.2}%", stats.cpu_usage))?;
+        state.serialize_field("total_memory", &format!("{} MB", stats.total_memory / MB))?;
+        state.serialize_field("used_memory", &format!("{} MB", stats.used_memory / MB))?;
+        state.serialize_field("total_swap", &format!("{} MB", stats.total_swap / MB))?;
+        state.serialize_field("used_swap", &format!("{} MB", stats.used_swap / MB))?;
+        state.serialize_field("system_name", &stats.system_name)?;
+        state.serialize_field("hostname", &stats.hostname)?;
+        state.serialize_field("total_cpus", &stats.total_cpus)?;
+        state.serialize_field("total_disk", &format!("{} MB", stats.total_disk / MB))?;
+        state.serialize_field("used_disk", &format!("{} MB", stats.used_disk / MB))?;
+        state.serialize_field("disk_usage", &format!("{:.2}%", stats.disk_usage))?;
+        state.serialize_field("task_count", &stats.task_count)?;
+        state.serialize_field("labels", &stats.labels)?;
         state.end()
     }
 }
 
-pub fn get_stats(sysinfo: &System, task_count: u64) -> SystemStats {
+pub fn get_stats(
+    sysinfo: &System,
+    task_count: u64,
+    labels: &HashMap<String, String>,
+) -> SystemStats {
+    let disks = Disks::new_with_refreshed_list();
+    let total_disk: u64 = disks.iter().map(|disk| disk.total_space()).sum();
+    let used_disk: u64 = disks
+        .iter()
+        .map(|disk| disk.total_space().saturating_sub(disk.available_space()))
+        .sum();
+
     SystemStats {
         cpu_usage: (sysinfo.global_cpu_usage() * 100.0).round() / 100.0,
-        total_memory: sysinfo.total_memory() / 1024 / 1024,
-        used_memory: sysinfo.used_memory() / 1024 / 1024,
-        total_swap: sysinfo.total_swap() / 1024 / 1024,
-        used_swap: sysinfo.used_swap() / 1024 / 1024,
+        total_memory: sysinfo.total_memory(),
+        used_memory: sysinfo.used_memory(),
+        total_swap: sysinfo.total_swap(),
+        used_swap: sysinfo.used_swap(),
         system_name: System::name().unwrap_or_else(|| "Unknown".to_string()),
         hostname: System::host_name().unwrap_or_else(|| "Unknown".to_string()),
         total_cpus: sysinfo.cpus().len() as u64,
-        disk_usage: {
-            let disks = Disks::new_with_refreshed_list();
-            let used_space: f32 = disks
-                .iter()
-                .map(|disk| disk.total_space() as f32 - disk.available_space() as f32)
-                .sum();
-            let total_space: f32 = disks.iter().map(|disk| disk.total_space() as f32).sum();
-            if total_space > 0.0 {
-                (used_space / total_space) * 100.0
-            } else {
-                0.0
-            }
+        total_disk,
+        used_disk,
+        disk_usage: if total_disk > 0 {
+            (used_disk as f32 / total_disk as f32) * 100.0
+        } else {
+            0.0
         },
         task_count,
+        labels: labels.clone(),
+    }
+}
+
+impl From<&SystemStats> for WorkerCapacity {
+    fn from(stats: &SystemStats) -> Self {
+        WorkerCapacity {
+            cores: stats.total_cpus,
+            memory: stats.total_memory,
+            disk: stats.total_disk,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats() -> SystemStats {
+        SystemStats {
+            cpu_usage: 12.345,
+            total_memory: 8192 * MB,
+            used_memory: 2048 * MB + 512,
+            total_swap: 0,
+            used_swap: 0,
+            system_name: "Linux".to_string(),
+            hostname: "node-1".to_string(),
+            total_cpus: 4,
+            total_disk: 100_000 * MB,
+            used_disk: 25_000 * MB,
+            disk_usage: 25.0,
+            task_count: 3,
+            labels: HashMap::from([("zone".to_string(), "a".to_string())]),
+        }
+    }
+
+    #[test]
+    fn numeric_stats_round_trip_through_json() {
+        let stats = stats();
+        let json = serde_json::to_string(&stats).unwrap();
+        let parsed: SystemStats = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&parsed).unwrap(),
+            serde_json::to_value(&stats).unwrap()
+        );
+        assert_eq!(parsed.used_memory, 2048 * MB + 512);
+        assert_eq!(parsed.cpu_usage, 12.345);
+        assert_eq!(parsed.labels["zone"], "a");
+        assert_eq!(WorkerCapacity::from(&parsed).memory, 8192 * MB);
+    }
+
+    #[test]
+    fn human_stats_spell_out_units() {
+        let stats = stats();
+        let human = serde_json::to_value(HumanStats(&stats)).unwrap();
+
+        assert_eq!(human["cpu_usage"], "12.35%");
+        assert_eq!(human["total_memory"], "8192 MB");
+        assert_eq!(human["used_memory"], "2048 MB");
+        assert_eq!(human["used_swap"], "0 MB");
+        assert_eq!(human["total_disk"], "100000 MB");
+        assert_eq!(human["disk_usage"], "25.00%");
+        assert_eq!(human["total_cpus"], 4);
+        assert_eq!(human["task_count"], 3);
+        assert_eq!(human["hostname"], "node-1");
+        assert_eq!(human["labels"]["zone"], "a");
     }
 }
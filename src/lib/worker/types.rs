@@ -1,55 +1,145 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, Notify};
 
-use crate::lib::tasks::types::{DockerError, Task};
-use std::{collections::HashMap, error::Error, fmt, sync::Arc};
+use crate::lib::{
+    store::store::Store,
+    tasks::types::{DockerError, OutputStream, Task, TaskEvent},
+};
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt,
+    sync::{Arc, atomic::AtomicU64},
+    time::Duration,
+};
 
-pub struct Worker {
+// * Number of tasks a worker executes at the same time unless configured
+pub const DEFAULT_MAX_CONCURRENT_TASKS: usize = 4;
+
+// * How long the container of a finished task is kept around for its logs
+// * unless configured
+pub const DEFAULT_CONTAINER_RETENTION: Duration = Duration::from_secs(600);
+
+pub struct Worker<R> {
     pub name: String,
-    pub queue: std::collections::VecDeque<Task>,
-    pub db: HashMap<String, Box<Task>>,
-    pub task_count: u64,
-    pub sysinfo: sysinfo::System,
+    pub queue: std::collections::VecDeque<TaskEvent>,
+    pub db: Arc<dyn Store<Task>>,
+    // * Tasks the store holds as running, kept up to date by `persist`
+    pub task_count: Arc<AtomicU64>,
+    // * Refreshed by `collect_stats` without the worker lock, so reading
+    // * stats never waits for a refresh
+    pub sysinfo: Arc<std::sync::Mutex<sysinfo::System>>,
+    pub runtime: Arc<R>,
+    // * Ids of the tasks an executor is currently working on. Queued events
+    // * for the same task wait until it is done.
+    pub running: HashSet<String>,
+    pub max_concurrent_tasks: usize,
+    // * Wakes `run_tasks` when a task is queued or an executor finishes
+    pub wakeup: Arc<Notify>,
+    // * Lets clients run commands inside task containers. Off unless the
+    // * worker is started with it.
+    pub allow_exec: bool,
+    // * Removes a task's container as soon as it is stopped on request
+    pub remove_on_stop: bool,
+    // * Containers of finished tasks are garbage collected after this long
+    pub container_retention: Duration,
+    // * Labels the worker registers with, which task placement rules match.
+    // * "arch" and "os" are always set.
+    pub labels: HashMap<String, String>,
 }
 
-#[derive(Deserialize, Debug)]
+// * Executor runs a single task against the runtime. It only holds shared
+// * handles, so it does its work without the worker lock.
+pub struct Executor<R> {
+    pub runtime: Arc<R>,
+    pub db: Arc<dyn Store<Task>>,
+    pub task_count: Arc<AtomicU64>,
+    pub remove_on_stop: bool,
+}
+
+// * SystemStats is the JSON schema served by GET /stats. Sizes are raw bytes
+// * and usages are percentages in the 0-100 range, so the manager can
+// * deserialize it straight back into this type.
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SystemStats {
+    // * Global CPU usage in percent
     pub cpu_usage: f32,
+    // * Bytes
     pub total_memory: u64,
+    // * Bytes
     pub used_memory: u64,
+    // * Bytes
     pub total_swap: u64,
+    // * Bytes
     pub used_swap: u64,
     pub system_name: String,
     pub hostname: String,
     pub total_cpus: u64,
+    // * Bytes, summed over all mounted disks
+    pub total_disk: u64,
+    // * Bytes, summed over all mounted disks
+    pub used_disk: u64,
+    // * Used disk space in percent
     pub disk_usage: f32,
     pub task_count: u64,
+    // * The worker's placement labels, so the manager learns them for
+    // * workers that never registered
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
+}
+
+// * HumanStats serializes SystemStats with units spelled out ("12.34%",
+// * "2048 MB") for people reading GET /stats?human=true
+pub struct HumanStats<'a>(pub &'a SystemStats);
+
+#[derive(Deserialize, Debug, Default)]
+pub struct StatsQuery {
+    #[serde(default)]
+    pub human: bool,
 }
 
-pub struct TaskServer {
-    pub worker: Arc<Mutex<Worker>>,
+// * ExecRequest is the body of POST /tasks/{id}/exec, and the first message a
+// * client sends on the exec WebSocket
+#[derive(Deserialize, Debug)]
+pub struct ExecRequest {
+    pub command: Vec<String>,
+}
+
+// * ExecMessage is what the exec WebSocket sends back to the client
+#[derive(Serialize, Debug)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ExecMessage {
+    Output { stream: OutputStream, data: String },
+    Exit { exit_code: i64 },
+    Error { message: String },
+}
+
+pub struct TaskServer<R> {
+    pub worker: Arc<Mutex<Worker<R>>>,
     pub address: String,
     pub port: String,
 }
 
 #[derive(Debug, Clone)]
 pub enum WorkerError {
-    NoTasksInQueue,
     InvalidStateTransition(String),
     DockerClientError(String),
+    PortConflict(String),
+    StoreError(String),
 }
 
 impl fmt::Display for WorkerError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            WorkerError::NoTasksInQueue => write!(f, "No tasks in the queue"),
             WorkerError::InvalidStateTransition(msg) => {
                 write!(f, "Invalid state transition: {}", msg)
             }
             WorkerError::DockerClientError(msg) => {
                 write!(f, "Docker client error: {}", msg)
             }
+            WorkerError::PortConflict(msg) => write!(f, "Port conflict: {}", msg),
+            WorkerError::StoreError(msg) => write!(f, "Task store error: {}", msg),
         }
     }
 }
@@ -59,15 +149,16 @@ impl Error for WorkerError {}
 impl From<WorkerError> for DockerError {
     fn from(worker_error: WorkerError) -> Self {
         match worker_error {
-            WorkerError::NoTasksInQueue => {
-                DockerError::ClientError("No tasks in queue".to_string())
-            }
             WorkerError::InvalidStateTransition(msg) => {
                 DockerError::ClientError(format!("Invalid state transition: {}", msg))
             }
             WorkerError::DockerClientError(msg) => DockerError::ClientError(msg),
+            WorkerError::PortConflict(msg) => {
+                DockerError::ClientError(format!("Port conflict: {}", msg))
+            }
+            WorkerError::StoreError(msg) => {
+                DockerError::ClientError(format!("Task store error: {}", msg))
+            }
         }
     }
 }
-
-pub type WorkerResult<T> = Result<T, WorkerError>;
@@ -1,240 +1,576 @@
 use error_stack::Report;
 use sysinfo::System;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, Notify, Semaphore};
 
-use super::types::Worker;
+use super::{
+    executor::persist,
+    types::{DEFAULT_CONTAINER_RETENTION, DEFAULT_MAX_CONCURRENT_TASKS, Executor, Worker},
+};
 use crate::lib::{
+    store::{store::Store, types::InMemoryStore},
     tasks::{
+        runtime::Runtime,
         state::valid_state_transition,
-        types::{new_config, DockerClient, DockerError, DockerResult, State, Task},
+        types::{ContainerStatus, DockerError, EventKind, State, Task, TaskEvent},
+    },
+    worker::{
+        stats::get_stats,
+        types::{SystemStats, WorkerError},
+    },
+};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
     },
-    worker::{stats::get_stats, types::{SystemStats, WorkerError}},
+    time::{Duration, SystemTime},
 };
-use std::{error::Error, fmt, sync::Arc, time::SystemTime};
 
-impl Worker {
-    pub fn new(name: &str) -> Self {
+impl<R: Runtime> Worker<R> {
+    pub fn new(name: &str, runtime: R) -> Self {
+        Self::with_store(name, runtime, Arc::new(InMemoryStore::new()))
+    }
+
+    pub fn with_store(name: &str, runtime: R, db: Arc<dyn Store<Task>>) -> Self {
         let sys = System::new_all();
+        let running = db
+            .list()
+            .map(|tasks| {
+                tasks
+                    .iter()
+                    .filter(|task| task.state == State::Running)
+                    .count()
+            })
+            .unwrap_or_default();
         Worker {
             name: name.to_string(),
             queue: std::collections::VecDeque::new(),
-            db: std::collections::HashMap::new(),
-            task_count: 0,
-            sysinfo: sys,
+            db,
+            task_count: Arc::new(AtomicU64::new(running as u64)),
+            sysinfo: Arc::new(std::sync::Mutex::new(sys)),
+            runtime: Arc::new(runtime),
+            running: HashSet::new(),
+            max_concurrent_tasks: DEFAULT_MAX_CONCURRENT_TASKS,
+            wakeup: Arc::new(Notify::new()),
+            allow_exec: false,
+            remove_on_stop: true,
+            container_retention: DEFAULT_CONTAINER_RETENTION,
+            labels: HashMap::from([
+                ("arch".to_string(), std::env::consts::ARCH.to_string()),
+                ("os".to_string(), std::env::consts::OS.to_string()),
+            ]),
         }
     }
 
-    async fn run_task(&mut self) -> DockerResult {
-        let task = match self.queue.pop_front() {
-            Some(task) => task,
-            None => {
-                println!("No tasks in queue");
-                let error_report = Report::new(WorkerError::NoTasksInQueue).change_context(
-                    DockerError::ClientError("No tasks available in worker queue".to_string()),
-                );
+    pub fn persist(&self, task: &Task) -> Result<(), Report<DockerError>> {
+        persist(self.db.as_ref(), &self.task_count, task)
+    }
 
-                return Err(error_report);
+    // * Reconciles the tasks loaded from the store with what the runtime
+    // * actually has, so a restarted worker picks up where it left off
+    pub async fn recover(&mut self) {
+        let tasks = self.get_tasks();
+        println!("Recovering {} task(s) from the store", tasks.len());
+
+        for mut task in tasks {
+            if task.state.is_terminal() {
+                continue;
             }
-        };
 
-        let persisted = self
-            .db
-            .entry(task.id.clone())
-            .or_insert_with(|| Box::new(task.clone()));
+            match (&task.state, task.container_id.clone()) {
+                (State::Running, Some(container_id)) => {
+                    match self.runtime.inspect(&container_id).await {
+                        Ok(inspect) if inspect.status == ContainerStatus::Exited => {
+                            task.exited(
+                                inspect.exit_code,
+                                format!(
+                                    "exited with code {:?} while the worker was down",
+                                    inspect.exit_code
+                                ),
+                            );
+                        }
+                        Ok(inspect) if inspect.status == ContainerStatus::Running => {
+                            task.assigned_ports = inspect.ports;
+                        }
+                        // Created but never started, or in a state the
+                        // runtime cannot tell, so it gets a fresh container
+                        Ok(inspect) => {
+                            println!(
+                                "Container {} of task {} is {:?}, running the task again",
+                                container_id, task.id, inspect.status
+                            );
+                            self.add_event(TaskEvent::new(EventKind::Restart, task.clone()));
+                        }
+                        Err(err) => {
+                            println!(
+                                "Container {} of task {} is gone: {:?}",
+                                container_id, task.id, err
+                            );
+                            task.exited(None, "container is gone");
+                        }
+                    }
+                }
+                // Never got a container, so it is started again
+                (State::Pending | State::Scheduled, _) => {
+                    task.transition(State::Scheduled, "queued again after a worker restart");
+                    self.add_event(TaskEvent::new(EventKind::Start, task.clone()));
+                }
+                // The stop was interrupted, so it is sent again
+                (State::Stopping, Some(_)) => {
+                    self.add_event(TaskEvent::new(EventKind::Stop, task.clone()));
+                }
+                _ => {
+                    task.transition(State::Failed, "lost during a worker restart");
+                    task.finish_time = Some(SystemTime::now());
+                }
+            }
+
+            println!("Recovered task {} as {:?}", task.id, task.state);
+            if let Err(err) = self.persist(&task) {
+                println!("Error saving recovered task {}: {:?}", task.id, err);
+            }
+        }
+    }
+
+    // * Takes the first queued event whose task no executor is working on,
+    // * checks it against the stored copy of the task and records the change,
+    // * so executors never race on the same task and port conflicts are
+    // * decided one task at a time
+    pub fn next_job(&mut self) -> Option<TaskEvent> {
+        while let Some(index) = self
+            .queue
+            .iter()
+            .position(|event| !self.running.contains(&event.task.id))
+        {
+            let event = self.queue.remove(index)?;
+            let task_id = event.task.id.clone();
+            match self.prepare(event) {
+                Ok(event) => {
+                    self.running.insert(task_id);
+                    return Some(event);
+                }
+                Err(err) => println!("Error running task {}: {:?}", task_id, err),
+            }
+        }
+
+        None
+    }
+
+    // * Called once an executor is done with a task
+    pub fn finish(&mut self, task_id: &str) {
+        self.running.remove(task_id);
+        self.wakeup.notify_one();
+    }
+
+    fn prepare(&self, mut event: TaskEvent) -> Result<TaskEvent, Report<DockerError>> {
+        let persisted = self.db.get(&event.task.id).map_err(|e| {
+            Report::new(WorkerError::StoreError(e.to_string())).change_context(
+                DockerError::ClientError(format!("Unable to load task {}", event.task.id)),
+            )
+        })?;
+        let current = persisted.unwrap_or_else(|| event.task.clone());
 
-        if !valid_state_transition(&persisted.state, &task.state) {
+        // Stop and signal act on the task as the worker knows it, the others
+        // bring a spec to run
+        let target = match &event.kind {
+            EventKind::Stop => State::Stopping,
+            EventKind::Signal { .. } => State::Running,
+            EventKind::Start => State::Scheduled,
+            EventKind::Restart | EventKind::Update => match current.state {
+                State::Running | State::Unknown => State::Restarting,
+                _ => State::Scheduled,
+            },
+        };
+
+        let valid = match &event.kind {
+            EventKind::Signal { .. } => current.state == State::Running,
+            // Only a live task can be updated
+            EventKind::Update => {
+                !current.state.is_terminal() && valid_state_transition(&current.state, &target)
+            }
+            _ => valid_state_transition(&current.state, &target),
+        };
+        if !valid {
             println!(
                 "Invalid state transition from {:?} to {:?}",
-                persisted.state, task.state
+                current.state, target
             );
+            // The manager still learns the task's state as of its change
+            if event.task.generation > current.generation {
+                let mut current = current.clone();
+                current.generation = event.task.generation;
+                self.persist(&current)?;
+            }
             let error_msg = format!(
-                "Invalid transition from {:?} to {:?}",
-                persisted.state, task.state
+                "Cannot {} task {} in state {:?}",
+                event.kind, current.id, current.state
             );
 
             // Using change_context to add more context to the error
             let error_report = Report::new(WorkerError::InvalidStateTransition(error_msg))
                 .change_context(DockerError::ClientError(format!(
                     "State transition validation failed for task {}",
-                    task.id
+                    current.id
                 )));
 
             return Err(error_report);
         }
 
-        match task.state {
-            State::Scheduled => {
-                println!("Task is scheduled, starting it now");
-                self.start_task(task).await
-            }
-            State::Completed => {
-                println!("Task is completed, stopping it now");
-                self.stop_task(task).await
-            }
-            _ => {
-                println!(
-                    "Invalid state for task: {:?} with id: {:?}",
-                    task.state, task.id
-                );
-                let error_report = Report::new(WorkerError::InvalidStateTransition(format!(
-                    "Invalid state {:?} for task {}",
-                    task.state, task.id
-                )))
-                .change_context(DockerError::ClientError(format!(
-                    "Task {} has invalid state {:?} for execution",
-                    task.id, task.state
-                )));
-
-                Err(error_report)
+        let mut task = match &event.kind {
+            EventKind::Stop | EventKind::Signal { .. } => current,
+            EventKind::Start | EventKind::Restart | EventKind::Update => {
+                let mut task = current.with_spec(&event.task);
+                task.restart_count = event.task.restart_count;
+                task.merge_history(&event.task.history);
+                task
             }
-        }
-    }
+        };
 
-    async fn start_task(&mut self, mut task: Task) -> DockerResult {
-        task.start_time = Some(SystemTime::now());
-        let config = new_config(task.clone());
+        // The worker's copy carries the manager's last change along
+        task.generation = task.generation.max(event.task.generation);
 
-        let docker_client = match DockerClient::new(config) {
-            Some(client) => client,
-            None => {
-                println!("Failed to create Docker client");
+        if let EventKind::Signal { .. } = event.kind {
+            event.task = task;
+            return Ok(event);
+        }
 
-                // Using change_context to provide more specific context
-                let error_report = Report::new(WorkerError::DockerClientError(
-                    "Docker client creation failed".to_string(),
-                ))
-                .change_context(DockerError::ClientError(format!(
-                    "Unable to initialize Docker client for task {}",
-                    task.id
-                )));
+        if target == State::Scheduled || target == State::Restarting {
+            let conflicts = self.port_conflicts(&task);
+            if !conflicts.is_empty() {
+                println!("Port conflict for task {}: {:?}", task.id, conflicts);
+                task.transition(State::Failed, "requested host ports are in use");
+                self.persist(&task)?;
 
+                let error_report = Report::new(WorkerError::PortConflict(conflicts.join(", ")))
+                    .change_context(DockerError::ClientError(format!(
+                        "Task {} requests host ports that are already in use",
+                        task.id
+                    )));
                 return Err(error_report);
             }
-        };
+        }
 
-        let result = docker_client.run().await;
-        match result {
-            Ok(response) => {
-                println!(
-                    "Task started successfully with container ID: {:?}",
-                    response.container_id
-                );
+        task.transition(
+            target,
+            format!("{} received by worker {}", event.kind, self.name),
+        );
+        self.persist(&task)?;
+        event.task = task;
+        Ok(event)
+    }
 
-                if let Some(container_id) = response.container_id.clone() {
-                    task.finish_time = Some(SystemTime::now());
-                    task.state = State::Running;
-                    task.container_id = Some(container_id);
-                    self.db.insert(task.id.clone(), Box::new(task.clone()));
-                }
-                Ok(response)
-            }
-            Err(err) => {
-                println!("Error running task: {:?}", err);
-                task.state = State::Failed;
-                Err(err)
-            }
+    // * Finds host ports requested by the task that another live task on this
+    // * worker already holds
+    fn port_conflicts(&self, task: &Task) -> Vec<String> {
+        self.get_tasks()
+            .into_iter()
+            .filter(|other| other.id != task.id && !other.state.is_terminal())
+            .flat_map(|other| {
+                let held = if other.assigned_ports.is_empty() {
+                    other.port_bindings.clone()
+                } else {
+                    other.assigned_ports.clone()
+                };
+                task.port_bindings.iter().filter_map(move |wanted| {
+                    held.iter()
+                        .find(|port| port.conflicts_with(wanted))
+                        .map(|_| {
+                            format!(
+                                "host port {}/{} is already bound by task {}",
+                                wanted.host_port.unwrap_or_default(),
+                                wanted.protocol.as_str(),
+                                other.id
+                            )
+                        })
+                })
+            })
+            .collect()
+    }
+
+    pub fn add_event(&mut self, event: TaskEvent) {
+        self.queue.push_back(event);
+        self.wakeup.notify_one();
+    }
+
+    pub fn executor(&self) -> Executor<R> {
+        Executor {
+            runtime: self.runtime.clone(),
+            db: self.db.clone(),
+            task_count: self.task_count.clone(),
+            remove_on_stop: self.remove_on_stop,
         }
     }
 
-    pub fn add_task(&mut self, task: Task) {
-        self.queue.push_back(task);
+    pub fn get_tasks(&self) -> Vec<Task> {
+        self.db.list().unwrap_or_else(|e| {
+            println!("Error listing tasks: {}", e);
+            Vec::new()
+        })
     }
+}
 
-    async fn stop_task(&mut self, mut task: Task) -> DockerResult {
-        let config = new_config(task.clone());
-        let docker_client = match DockerClient::new(config) {
-            Some(client) => client,
-            None => {
-                println!("Failed to create Docker client");
-                // Using change_context for richer context about the stop operation
-                let error_report = Report::new(WorkerError::DockerClientError(
-                    "Docker client creation failed during stop operation".to_string(),
-                ))
-                .change_context(DockerError::ClientError(format!(
-                    "Unable to initialize Docker client to stop task {}",
-                    task.id
-                )));
+// * Hands queued tasks to at most `max_concurrent_tasks` executors at a time.
+// * The worker lock is only held to pick a task and to record that it is done,
+// * so a slow image pull doesn't block the API or the other tasks.
+pub async fn run_tasks<R: Runtime>(worker: Arc<Mutex<Worker<R>>>) {
+    let (wakeup, executor, permits) = {
+        let worker = worker.lock().await;
+        (
+            worker.wakeup.clone(),
+            worker.executor(),
+            Arc::new(Semaphore::new(worker.max_concurrent_tasks.max(1))),
+        )
+    };
 
-                return Err(error_report);
-            }
+    loop {
+        let permit = match permits.clone().acquire_owned().await {
+            Ok(permit) => permit,
+            Err(_) => return,
         };
 
-        let container_id = match task.container_id.clone() {
-            Some(id) => id,
-            None => {
-                println!("No container_id for task");
-                // Using error-stack here too for consistency
-                let error_report = Report::new(WorkerError::DockerClientError(format!(
-                    "Task {} has no container_id for stop operation",
-                    task.id
-                )))
-                .change_context(DockerError::ClientError(format!(
-                    "Cannot stop task {} without container_id",
-                    task.id
-                )));
+        let Some(event) = worker.lock().await.next_job() else {
+            drop(permit);
+            wakeup.notified().await;
+            continue;
+        };
 
-                return Err(error_report);
+        let worker = worker.clone();
+        let executor = executor.clone();
+        tokio::spawn(async move {
+            let task_id = event.task.id.clone();
+            match executor.execute(event).await {
+                Ok(response) => {
+                    println!(
+                        "{} of task {} completed successfully: {:?}",
+                        response.action.unwrap_or_default(),
+                        task_id,
+                        response.container_id
+                    );
+                }
+                Err(err) => {
+                    println!("Error running task: {:?}", err);
+                }
             }
+            worker.lock().await.finish(&task_id);
+            drop(permit);
+        });
+    }
+}
+
+// * Inspects the containers of running tasks and records the ones that exited
+// * on their own, so the manager learns about crashes through GET /tasks
+pub async fn monitor_tasks<R: Runtime>(worker: Arc<Mutex<Worker<R>>>, interval: Duration) {
+    loop {
+        tokio::time::sleep(interval).await;
+
+        let (executor, tasks) = {
+            let worker = worker.lock().await;
+            let tasks: Vec<Task> = worker
+                .get_tasks()
+                .into_iter()
+                .filter(|task| task.state == State::Running && !worker.running.contains(&task.id))
+                .collect();
+            (worker.executor(), tasks)
         };
 
-        let result = docker_client.stop(&container_id).await;
-        match result {
-            Ok(response) => {
-                task.state = State::Completed;
-                task.finish_time = Some(SystemTime::now());
+        for task in tasks {
+            let Some(container_id) = task.container_id.clone() else {
+                continue;
+            };
 
-                self.db.insert(task.id.clone(), Box::new(task.clone()));
-                println!(
-                    "Stopped and removed task with container ID: {:?}",
-                    response.container_id
-                );
+            let exit_code = match executor.runtime.inspect(&container_id).await {
+                Ok(inspect) if inspect.status == ContainerStatus::Exited => inspect.exit_code,
+                Ok(_) => continue,
+                Err(err) => {
+                    println!(
+                        "Container {} of task {} is gone: {:?}",
+                        container_id, task.id, err
+                    );
+                    None
+                }
+            };
 
-                Ok(response)
+            let worker = worker.lock().await;
+            // An executor may have picked the task up while it was inspected
+            if worker.running.contains(&task.id) {
+                continue;
             }
-            Err(err) => {
-                println!("Error stopping task: {:?}", err);
-                Err(err)
+            let Ok(Some(mut current)) = worker.db.get(&task.id) else {
+                continue;
+            };
+            if current.state != State::Running {
+                continue;
             }
-        }
-    }
 
-    pub fn get_tasks(&self) -> Vec<Task> {
-        self.db.values().map(|task| task.as_ref().clone()).collect()
+            current.exited(
+                exit_code,
+                format!("container exited with code {:?}", exit_code),
+            );
+            println!(
+                "Task {} exited with code {:?}, now {:?}",
+                current.id, exit_code, current.state
+            );
+            if let Err(err) = worker.persist(&current) {
+                println!("Error saving exited task {}: {:?}", current.id, err);
+            }
+        }
     }
 }
 
-pub async fn run_tasks(worker: Arc<Mutex<Worker>>) {
+// * Removes the containers of tasks that finished more than the retention
+// * period ago, so exited containers don't pile up on the host
+pub async fn collect_garbage<R: Runtime>(worker: Arc<Mutex<Worker<R>>>, interval: Duration) {
     loop {
-        if !worker.lock().await.queue.is_empty() {
-            match worker.lock().await.run_task().await {
-                Ok(response) => {
-                    println!("Task completed successfully: {:?}", response.container_id);
-                }
-                Err(err) => {
-                    println!("Error running task: {:?}", err);
+        tokio::time::sleep(interval).await;
+
+        let (executor, retention, tasks) = {
+            let worker = worker.lock().await;
+            let tasks: Vec<Task> = worker
+                .get_tasks()
+                .into_iter()
+                .filter(|task| {
+                    task.state.is_terminal()
+                        && task.container_id.is_some()
+                        && !worker.running.contains(&task.id)
+                })
+                .collect();
+            (worker.executor(), worker.container_retention, tasks)
+        };
+
+        let now = SystemTime::now();
+        for task in tasks {
+            let Some(container_id) = task.container_id.clone() else {
+                continue;
+            };
+            let finished = task
+                .finish_time
+                .or_else(|| task.last_transition())
+                .unwrap_or(now);
+            if now.duration_since(finished).unwrap_or_default() < retention {
+                continue;
+            }
+
+            if let Err(err) = executor.runtime.remove(&container_id).await {
+                // A container the runtime no longer knows is gone all the same
+                if executor.runtime.inspect(&container_id).await.is_ok() {
+                    println!("Error removing container {}: {:?}", container_id, err);
+                    continue;
                 }
             }
-        } else {
-            println!("No tasks in queue, waiting...");
-        }
+            println!(
+                "Removed container {} of finished task {}",
+                container_id, task.id
+            );
 
-        // Sleep for a while before checking the queue again
-        tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+            let worker = worker.lock().await;
+            // The task may have been restarted in a new container meanwhile
+            let Ok(Some(mut current)) = worker.db.get(&task.id) else {
+                continue;
+            };
+            if worker.running.contains(&task.id)
+                || current.container_id.as_deref() != Some(container_id.as_str())
+            {
+                continue;
+            }
+            current.container_id = None;
+            if let Err(err) = worker.persist(&current) {
+                println!("Error saving task {}: {:?}", current.id, err);
+            }
+        }
     }
 }
 
-pub async fn collect_stats(worker: Arc<Mutex<Worker>>) {
+// * Keeps the system readings fresh. CPU usage is measured between two
+// * refreshes, so it only means something once this ran twice.
+pub async fn collect_stats<R: Runtime>(worker: Arc<Mutex<Worker<R>>>) {
+    let sysinfo = worker.lock().await.sysinfo.clone();
     loop {
         println!("Collecting system stats... ");
-        let mut worker_guard = worker.lock().await;
-        worker_guard.sysinfo.refresh_all();
-        let _stats = get_stats(&worker_guard.sysinfo, worker_guard.task_count);
+        let sysinfo = sysinfo.clone();
+        let refreshed =
+            tokio::task::spawn_blocking(move || sysinfo.lock().unwrap().refresh_all()).await;
+        if let Err(e) = refreshed {
+            eprintln!("Error refreshing system stats: {}", e);
+        }
         tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
     }
 }
 
-pub async fn get_system_stats(worker: Arc<Mutex<Worker>>) -> SystemStats {
-    let mut worker_guard = worker.lock().await;
-    worker_guard.sysinfo.refresh_all();
-    get_stats(&worker_guard.sysinfo, worker_guard.task_count)
+// * Stats as of the last refresh by `collect_stats`
+pub async fn get_system_stats<R: Runtime>(worker: Arc<Mutex<Worker<R>>>) -> SystemStats {
+    let (sysinfo, task_count, labels) = {
+        let worker = worker.lock().await;
+        (
+            worker.sysinfo.clone(),
+            worker.task_count.load(Ordering::Relaxed),
+            worker.labels.clone(),
+        )
+    };
+    let sysinfo = sysinfo.lock().unwrap();
+    get_stats(&sysinfo, task_count, &labels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lib::tasks::types::{MemoryRuntime, PortBinding, Protocol};
+
+    fn task(state: State, ports: &[(Option<&str>, Option<u16>, Protocol)]) -> Task {
+        Task {
+            state,
+            port_bindings: ports
+                .iter()
+                .map(|(host_ip, host_port, protocol)| PortBinding {
+                    container_port: 80,
+                    protocol: *protocol,
+                    host_ip: host_ip.map(str::to_string),
+                    host_port: *host_port,
+                })
+                .collect(),
+            ..Task::default()
+        }
+    }
+
+    #[test]
+    fn port_conflicts_only_counts_live_tasks_holding_the_same_socket() {
+        let worker = Worker::new("w1", MemoryRuntime::new());
+        let holders = [
+            task(State::Running, &[(None, Some(8080), Protocol::Tcp)]),
+            task(
+                State::Running,
+                &[(Some("10.0.0.1"), Some(9090), Protocol::Tcp)],
+            ),
+            task(State::Completed, &[(None, Some(7070), Protocol::Tcp)]),
+            task(State::Running, &[(None, None, Protocol::Tcp)]),
+        ];
+        for holder in &holders {
+            worker.db.put(&holder.id, holder.clone()).unwrap();
+        }
+        let conflicts = |wanted: &[(Option<&str>, Option<u16>, Protocol)]| {
+            worker.port_conflicts(&task(State::Scheduled, wanted)).len()
+        };
+
+        assert_eq!(conflicts(&[(None, Some(8080), Protocol::Tcp)]), 1);
+        assert_eq!(conflicts(&[(None, Some(8080), Protocol::Udp)]), 0);
+        assert_eq!(
+            conflicts(&[(Some("0.0.0.0"), Some(9090), Protocol::Tcp)]),
+            1
+        );
+        assert_eq!(
+            conflicts(&[(Some("10.0.0.2"), Some(9090), Protocol::Tcp)]),
+            0
+        );
+        assert_eq!(
+            conflicts(&[(Some("10.0.0.1"), Some(8080), Protocol::Tcp)]),
+            1
+        );
+        // A finished task no longer holds its port
+        assert_eq!(conflicts(&[(None, Some(7070), Protocol::Tcp)]), 0);
+        assert_eq!(conflicts(&[(None, None, Protocol::Tcp)]), 0);
+
+        // A task never conflicts with itself
+        let held = &holders[0];
+        assert!(worker.port_conflicts(held).is_empty());
+    }
 }